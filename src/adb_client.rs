@@ -0,0 +1,219 @@
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use crate::device::{parse_devices, Device};
+use crate::DevInsightError;
+
+const DEFAULT_SERVER_PORT: u16 = 5037;
+
+// Talks to the adb server over its host protocol instead of spawning the adb binary.
+// Every request is a 4-digit hex length followed by the service name; the server answers
+// OKAY, or FAIL followed by a length-prefixed message.
+#[derive(Clone)]
+pub struct AdbClient {
+    address: String,
+}
+
+impl AdbClient {
+    pub fn new(address: String) -> Self {
+        Self { address }
+    }
+
+    // The local server, honouring ANDROID_ADB_SERVER_PORT like the adb binary does
+    pub fn local() -> Self {
+        let port = std::env::var("ANDROID_ADB_SERVER_PORT")
+            .ok()
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(DEFAULT_SERVER_PORT);
+        Self::new(format!("127.0.0.1:{}", port))
+    }
+
+    pub fn devices(&self) -> Result<Vec<Device>, DevInsightError> {
+        let mut stream = self.request("host:devices-l")?;
+        let list = read_length_prefixed(&mut stream)?;
+        Ok(parse_devices(&list))
+    }
+
+    pub fn get_state(&self, serial: Option<&str>) -> Result<String, DevInsightError> {
+        let service = match serial {
+            Some(serial) => format!("host-serial:{}:get-state", serial),
+            None => "host:get-state".to_string(),
+        };
+        let mut stream = self.request(&service)?;
+        read_length_prefixed(&mut stream)
+    }
+
+    // Run a command on the device and return the open stream of its output
+    pub fn shell(&self, serial: Option<&str>, command: &str) -> Result<BufReader<TcpStream>, DevInsightError> {
+        let transport = match serial {
+            Some(serial) => format!("host:transport:{}", serial),
+            None => "host:transport-any".to_string(),
+        };
+        let mut stream = self.request(&transport)?;
+        send_request(&mut stream, &format!("shell:{}", command))?;
+        read_status(&mut stream)?;
+        Ok(BufReader::new(stream))
+    }
+
+    fn request(&self, service: &str) -> Result<TcpStream, DevInsightError> {
+        let mut stream = TcpStream::connect(&self.address).map_err(|e| {
+            DevInsightError::AdbServerError(format!("cannot connect to adb server at {}: {}", self.address, e))
+        })?;
+        send_request(&mut stream, service)?;
+        read_status(&mut stream)?;
+        Ok(stream)
+    }
+}
+
+fn send_request(stream: &mut TcpStream, service: &str) -> Result<(), DevInsightError> {
+    stream.write_all(format!("{:04x}{}", service.len(), service).as_bytes())?;
+    Ok(())
+}
+
+fn read_status(stream: &mut TcpStream) -> Result<(), DevInsightError> {
+    let mut status = [0u8; 4];
+    stream.read_exact(&mut status)?;
+    match &status {
+        b"OKAY" => Ok(()),
+        b"FAIL" => Err(device_error(read_length_prefixed(stream)?)),
+        other => Err(DevInsightError::AdbServerError(format!(
+            "unexpected response '{}'",
+            String::from_utf8_lossy(other)
+        ))),
+    }
+}
+
+fn read_length_prefixed(stream: &mut TcpStream) -> Result<String, DevInsightError> {
+    let mut length = [0u8; 4];
+    stream.read_exact(&mut length)?;
+    let length = std::str::from_utf8(&length)
+        .ok()
+        .and_then(|hex| usize::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| DevInsightError::AdbServerError("malformed length prefix".to_string()))?;
+    let mut payload = vec![0u8; length];
+    stream.read_exact(&mut payload)?;
+    Ok(String::from_utf8_lossy(&payload).into_owned())
+}
+
+// Turn the server's FAIL message into the device state it describes
+fn device_error(message: String) -> DevInsightError {
+    if message.contains("not found") || message.contains("no devices") {
+        DevInsightError::DeviceNotFound(message)
+    } else if message.contains("offline") {
+        DevInsightError::DeviceOffline(message)
+    } else if message.contains("unauthorized") {
+        DevInsightError::DeviceUnauthorized(message)
+    } else {
+        DevInsightError::AdbServerError(message)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    // A fake adb server. Each connection it accepts answers its requests in turn, checking
+    // that each one asks for the expected service, then closes.
+    pub(crate) fn serve(connections: Vec<Vec<(&str, Vec<u8>)>>) -> (AdbClient, JoinHandle<()>) {
+        let connections: Vec<Vec<(String, Vec<u8>)>> = connections
+            .into_iter()
+            .map(|exchanges| exchanges.into_iter().map(|(service, reply)| (service.to_string(), reply)).collect())
+            .collect();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = AdbClient::new(listener.local_addr().unwrap().to_string());
+        let server = std::thread::spawn(move || {
+            for exchanges in connections {
+                let (mut stream, _) = listener.accept().unwrap();
+                for (service, reply) in exchanges {
+                    let mut length = [0u8; 4];
+                    stream.read_exact(&mut length).unwrap();
+                    let length = usize::from_str_radix(std::str::from_utf8(&length).unwrap(), 16).unwrap();
+                    let mut request = vec![0u8; length];
+                    stream.read_exact(&mut request).unwrap();
+                    assert_eq!(String::from_utf8(request).unwrap(), service);
+                    stream.write_all(&reply).unwrap();
+                }
+            }
+        });
+        (client, server)
+    }
+
+    pub(crate) fn okay(payload: &str) -> Vec<u8> {
+        format!("OKAY{:04x}{}", payload.len(), payload).into_bytes()
+    }
+
+    pub(crate) fn fail(message: &str) -> Vec<u8> {
+        format!("FAIL{:04x}{}", message.len(), message).into_bytes()
+    }
+
+    #[test]
+    fn devices() {
+        let list = "emulator-5554          device product:sdk model:Pixel_7 device:emu64a transport_id:1\n\
+                    R58M123456             unauthorized transport_id:2\n";
+        let (client, server) = serve(vec![vec![("host:devices-l", okay(list))]]);
+
+        let devices = client.devices().unwrap();
+        server.join().unwrap();
+        assert_eq!(devices.len(), 2);
+        assert_eq!((devices[0].serial.as_str(), devices[0].model.as_deref()), ("emulator-5554", Some("Pixel_7")));
+        assert!(devices[0].is_online());
+        assert_eq!(devices[1].state, "unauthorized");
+    }
+
+    #[test]
+    fn get_state() {
+        let (client, server) = serve(vec![
+            vec![("host-serial:emulator-5554:get-state", okay("device"))],
+            vec![("host:get-state", okay("offline"))],
+        ]);
+
+        assert_eq!(client.get_state(Some("emulator-5554")).unwrap(), "device");
+        assert_eq!(client.get_state(None).unwrap(), "offline");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn shell_switches_transport_then_streams_output() {
+        let (client, server) = serve(vec![vec![
+            ("host:transport:emulator-5554", b"OKAY".to_vec()),
+            ("shell:getprop ro.product.model", b"OKAYPixel 7\n".to_vec()),
+        ]]);
+
+        let mut output = String::new();
+        client.shell(Some("emulator-5554"), "getprop ro.product.model").unwrap().read_to_string(&mut output).unwrap();
+        server.join().unwrap();
+        assert_eq!(output, "Pixel 7\n");
+    }
+
+    #[test]
+    fn shell_without_serial_uses_any_transport() {
+        let (client, server) = serve(vec![vec![
+            ("host:transport-any", b"OKAY".to_vec()),
+            ("shell:true", b"OKAY".to_vec()),
+        ]]);
+
+        client.shell(None, "true").unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn failures_map_to_device_errors() {
+        let (client, server) = serve(vec![
+            vec![("host-serial:gone:get-state", fail("device 'gone' not found"))],
+            vec![("host:transport-any", fail("no devices/emulators found"))],
+            vec![("host-serial:emulator-5554:get-state", fail("device offline"))],
+            vec![("host-serial:R58M123456:get-state", fail("device unauthorized.\nThis adb server's $ADB_VENDOR_KEYS is not set"))],
+            vec![("host:devices-l", fail("protocol fault"))],
+            vec![("host:devices-l", b"WHAT".to_vec())],
+        ]);
+
+        assert!(matches!(client.get_state(Some("gone")), Err(DevInsightError::DeviceNotFound(_))));
+        assert!(matches!(client.shell(None, "true"), Err(DevInsightError::DeviceNotFound(_))));
+        assert!(matches!(client.get_state(Some("emulator-5554")), Err(DevInsightError::DeviceOffline(_))));
+        assert!(matches!(client.get_state(Some("R58M123456")), Err(DevInsightError::DeviceUnauthorized(_))));
+        assert!(matches!(client.devices(), Err(DevInsightError::AdbServerError(message)) if message == "protocol fault"));
+        assert!(matches!(client.devices(), Err(DevInsightError::AdbServerError(_))));
+        server.join().unwrap();
+    }
+}
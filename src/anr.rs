@@ -0,0 +1,203 @@
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local};
+use crate::block::{Block, BlockGrouper};
+use crate::tui::LogEntry;
+
+const ANR_TAG: &str = "ActivityManager";
+
+// The ANR block is considered complete once its lines stop arriving for this long
+const ANR_IDLE: Duration = Duration::from_secs(1);
+
+pub struct Anr {
+    pub timestamp: DateTime<Local>,
+    pub package: String,
+    pub component: Option<String>,
+    pub pid: Option<u32>,
+    pub reason: String,
+    pub load: Option<String>,
+    pub cpu_usage: Vec<String>,
+    in_cpu_usage: bool,
+}
+
+impl Anr {
+    // "ANR in com.example.app (com.example.app/.MainActivity)"
+    fn new(timestamp: DateTime<Local>, header: &str) -> Self {
+        let (package, component) = match header.split_once(" (") {
+            Some((package, component)) => (package, Some(component.trim_end_matches(')').to_string())),
+            None => (header, None),
+        };
+        Self {
+            timestamp,
+            package: package.trim().to_string(),
+            component,
+            pid: None,
+            reason: String::new(),
+            load: None,
+            cpu_usage: Vec::new(),
+            in_cpu_usage: false,
+        }
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = format!("ANR in {}", self.package);
+        if let Some(component) = &self.component {
+            report.push_str(&format!(" ({})", component));
+        }
+        if let Some(pid) = self.pid {
+            report.push_str(&format!("\nPID: {}", pid));
+        }
+        report.push_str(&format!("\nReason: {}", self.reason));
+        if let Some(load) = &self.load {
+            report.push_str(&format!("\nLoad: {}", load));
+        }
+        if !self.cpu_usage.is_empty() {
+            report.push_str("\nCPU usage:");
+            for line in &self.cpu_usage {
+                report.push_str(&format!("\n  {}", line));
+            }
+        }
+        report
+    }
+}
+
+impl Block for Anr {
+    // Apply one line of the block, returning false if it doesn't belong to this ANR
+    fn apply_line(&mut self, line: &str) -> bool {
+        let trimmed = line.trim();
+
+        if let Some(pid) = trimmed.strip_prefix("PID: ") {
+            self.pid = pid.trim().parse().ok();
+        } else if let Some(reason) = trimmed.strip_prefix("Reason: ") {
+            self.reason = reason.to_string();
+        } else if let Some(load) = trimmed.strip_prefix("Load: ") {
+            self.load = Some(load.to_string());
+        } else if trimmed.starts_with("CPU usage from") {
+            self.in_cpu_usage = true;
+        } else if self.in_cpu_usage {
+            if !trimmed.contains('%') {
+                return false;
+            }
+            self.cpu_usage.push(trimmed.to_string());
+        }
+        // Other header lines (Parent, ErrorId, Frozen, PSI output) aren't kept
+        true
+    }
+}
+
+// Groups ActivityManager's "ANR in <package>" block (reason, load average and CPU usage)
+// into a single Anr record.
+pub struct AnrDetector {
+    blocks: BlockGrouper<Anr>,
+}
+
+impl AnrDetector {
+    pub fn new() -> Self {
+        Self { blocks: BlockGrouper::new(ANR_IDLE) }
+    }
+
+    pub fn feed(&mut self, entry: &LogEntry) -> Vec<Anr> {
+        self.blocks.feed(entry, ANR_TAG, |line| {
+            line.trim().strip_prefix("ANR in ").map(|header| Anr::new(entry.timestamp, header))
+        })
+    }
+
+    pub fn flush_stale(&mut self) -> Option<Anr> {
+        self.blocks.flush_stale(Instant::now())
+    }
+
+    pub fn finish(&mut self) -> Option<Anr> {
+        self.blocks.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as TimeDelta;
+
+    const BLOCK: &[&str] = &[
+        "ANR in com.example.app (com.example.app/.MainActivity)",
+        "PID: 4242",
+        "Reason: Input dispatching timed out (Waited 5001ms for MotionEvent)",
+        "Parent: com.example.app/.MainActivity",
+        "Load: 1.2 / 0.9 / 0.5",
+        "CPU usage from 0ms to 5012ms later:",
+        "  45% 4242/com.example.app: 40% user + 5% kernel",
+        "  10% 1000/system_server: 6% user + 4% kernel",
+    ];
+
+    fn entry(at: DateTime<Local>, pid: u32, tag: &str, message: &str) -> LogEntry {
+        let mut entry = LogEntry::new(at, 'E', tag, message);
+        entry.pid = Some(pid);
+        entry
+    }
+
+    fn feed_block(detector: &mut AnrDetector, at: DateTime<Local>) -> Vec<Anr> {
+        BLOCK.iter().flat_map(|line| detector.feed(&entry(at, 1000, ANR_TAG, line))).collect()
+    }
+
+    #[test]
+    fn block_is_grouped_into_one_anr() {
+        let now = Local::now();
+        let mut detector = AnrDetector::new();
+        assert!(feed_block(&mut detector, now).is_empty());
+        let finished = detector.feed(&entry(now, 1000, ANR_TAG, "Start proc 5000:com.other/u0a1 for service"));
+
+        assert_eq!(finished.len(), 1);
+        let anr = &finished[0];
+        assert_eq!(anr.package, "com.example.app");
+        assert_eq!(anr.component.as_deref(), Some("com.example.app/.MainActivity"));
+        assert_eq!(anr.pid, Some(4242));
+        assert_eq!(anr.reason, "Input dispatching timed out (Waited 5001ms for MotionEvent)");
+        assert_eq!(anr.load.as_deref(), Some("1.2 / 0.9 / 0.5"));
+        assert_eq!(anr.cpu_usage.len(), 2);
+        assert!(anr.report().starts_with("ANR in com.example.app (com.example.app/.MainActivity)\nPID: 4242\nReason: "));
+    }
+
+    #[test]
+    fn header_without_component() {
+        let mut detector = AnrDetector::new();
+        detector.feed(&entry(Local::now(), 1000, ANR_TAG, "ANR in com.example.app"));
+        let anr = detector.finish().unwrap();
+        assert_eq!((anr.package.as_str(), anr.component), ("com.example.app", None));
+    }
+
+    #[test]
+    fn whole_block_in_one_entry() {
+        let mut detector = AnrDetector::new();
+        detector.feed(&entry(Local::now(), 1000, ANR_TAG, &BLOCK.join("\n")));
+        let anr = detector.finish().unwrap();
+        assert_eq!((anr.pid, anr.cpu_usage.len()), (Some(4242), 2));
+    }
+
+    #[test]
+    fn another_logger_ends_the_block() {
+        let now = Local::now();
+        let mut detector = AnrDetector::new();
+        detector.feed(&entry(now, 1000, ANR_TAG, BLOCK[0]));
+        let finished = detector.feed(&entry(now, 2000, ANR_TAG, "PID: 1"));
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].pid, None);
+        assert!(detector.finish().is_none());
+    }
+
+    #[test]
+    fn device_time_gap_ends_the_block() {
+        let now = Local::now();
+        let mut detector = AnrDetector::new();
+        detector.feed(&entry(now, 1000, ANR_TAG, BLOCK[0]));
+        assert!(detector.feed(&entry(now + TimeDelta::milliseconds(500), 3000, "Other", "busy")).is_empty());
+        let finished = detector.feed(&entry(now + TimeDelta::seconds(2), 3000, "Other", "busy"));
+        assert_eq!(finished.len(), 1);
+    }
+
+    #[test]
+    fn quiet_stream_times_out() {
+        let mut detector = AnrDetector::new();
+        detector.feed(&entry(Local::now(), 1000, ANR_TAG, BLOCK[0]));
+        assert!(detector.blocks.flush_stale(Instant::now()).is_none());
+        let anr = detector.blocks.flush_stale(Instant::now() + ANR_IDLE).unwrap();
+        assert_eq!(anr.package, "com.example.app");
+        assert!(detector.finish().is_none());
+    }
+}
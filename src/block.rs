@@ -0,0 +1,168 @@
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local};
+use crate::tui::LogEntry;
+
+// A report one logger writes as a run of lines: a Java crash, an ANR, ...
+pub trait Block {
+    // Apply one line, returning false if it doesn't belong to the block
+    fn apply_line(&mut self, line: &str) -> bool;
+}
+
+// Groups runs of lines into blocks for the crash, ANR and tombstone detectors. A block
+// ends at the next header, at a line that doesn't fit it or comes from another pid, at a
+// gap in device time, or once its lines stop arriving for `idle`.
+pub struct BlockGrouper<T> {
+    current: Option<T>,
+    logger_pid: Option<u32>,
+    last_timestamp: DateTime<Local>,
+    last_seen: Instant,
+    idle: Duration,
+}
+
+impl<T> BlockGrouper<T> {
+    pub fn new(idle: Duration) -> Self {
+        Self {
+            current: None,
+            logger_pid: None,
+            last_timestamp: Local::now(),
+            last_seen: Instant::now(),
+            idle,
+        }
+    }
+
+    pub fn current(&self) -> Option<&T> {
+        self.current.as_ref()
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut T> {
+        self.current.as_mut()
+    }
+
+    // Start a new block, returning the one it interrupts
+    pub fn start(&mut self, block: T, pid: Option<u32>) -> Option<T> {
+        self.logger_pid = pid;
+        self.current.replace(block)
+    }
+
+    pub fn take(&mut self) -> Option<T> {
+        self.current.take()
+    }
+
+    // Whether a line from `pid` can continue the block in progress
+    pub fn is_logger(&self, pid: Option<u32>) -> bool {
+        pid == self.logger_pid
+    }
+
+    // Hand the block in progress over to another logger, as when crash_dump picks up
+    // from the crashing process's libc line
+    pub fn hand_over(&mut self, pid: Option<u32>) {
+        self.logger_pid = pid;
+    }
+
+    // A long gap in device time means the block is over, even on a busy stream. Dividers
+    // and unparsed lines carry the host's clock, not the device's, so they can't tell.
+    pub fn end_after_gap(&mut self, entry: &LogEntry) -> Option<T> {
+        if entry.priority != '?'
+            && (entry.timestamp - self.last_timestamp).num_milliseconds() > self.idle.as_millis() as i64 {
+            self.current.take()
+        } else {
+            None
+        }
+    }
+
+    // Note that a line for the block arrived
+    pub fn touch(&mut self, entry: &LogEntry) {
+        self.last_timestamp = entry.timestamp;
+        self.last_seen = Instant::now();
+    }
+
+    // Finish the block in progress if its lines have stopped arriving by `now`
+    pub fn flush_stale(&mut self, now: Instant) -> Option<T> {
+        if now.saturating_duration_since(self.last_seen) >= self.idle {
+            self.current.take()
+        } else {
+            None
+        }
+    }
+
+    // Finish whatever is in progress, e.g. when the stream ends
+    pub fn finish(&mut self) -> Option<T> {
+        self.current.take()
+    }
+}
+
+impl<T: Block> BlockGrouper<T> {
+    // Feed an entry. Lines of `tag` entries that `header` recognises start a block; the
+    // others extend the one in progress.
+    pub fn feed(&mut self, entry: &LogEntry, tag: &str, mut header: impl FnMut(&str) -> Option<T>) -> Vec<T> {
+        let mut finished: Vec<T> = self.end_after_gap(entry).into_iter().collect();
+        if entry.tag != tag {
+            return finished;
+        }
+
+        for line in entry.message.lines() {
+            if let Some(block) = header(line) {
+                finished.extend(self.start(block, entry.pid));
+            } else if let Some(block) = &mut self.current {
+                if entry.pid != self.logger_pid || !block.apply_line(line) {
+                    finished.extend(self.current.take());
+                }
+            }
+        }
+
+        self.touch(entry);
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as TimeDelta;
+
+    const TAG: &str = "Report";
+
+    // Lines after a "BEGIN" header, up to the first one that says "END"
+    struct Lines(Vec<String>);
+
+    impl Block for Lines {
+        fn apply_line(&mut self, line: &str) -> bool {
+            self.0.push(line.to_string());
+            line != "END"
+        }
+    }
+
+    fn entry(at: DateTime<Local>, priority: char, tag: &str, message: &str) -> LogEntry {
+        let mut entry = LogEntry::new(at, priority, tag, message);
+        entry.pid = (priority != '?').then_some(1000);
+        entry
+    }
+
+    fn feed(grouper: &mut BlockGrouper<Lines>, entry: &LogEntry) -> Vec<Lines> {
+        grouper.feed(entry, TAG, |line| (line == "BEGIN").then(|| Lines(Vec::new())))
+    }
+
+    #[test]
+    fn divider_inside_a_block_keeps_it_open() {
+        // A capture from a day ago, so the device's clock is far from the host's
+        let device_time = Local::now() - TimeDelta::days(1);
+        let mut grouper = BlockGrouper::new(Duration::from_secs(1));
+        feed(&mut grouper, &entry(device_time, 'E', TAG, "BEGIN"));
+        feed(&mut grouper, &entry(device_time, 'E', TAG, "first"));
+        assert!(feed(&mut grouper, &entry(Local::now(), '?', "UNKNOWN", "--------- beginning of crash")).is_empty());
+        feed(&mut grouper, &entry(device_time, 'E', TAG, "second"));
+
+        let block = grouper.finish().unwrap();
+        assert_eq!(block.0, ["first", "second"]);
+    }
+
+    #[test]
+    fn device_time_gap_ends_the_block() {
+        let device_time = Local::now() - TimeDelta::days(1);
+        let mut grouper = BlockGrouper::new(Duration::from_secs(1));
+        feed(&mut grouper, &entry(device_time, 'E', TAG, "BEGIN"));
+        let finished = feed(&mut grouper, &entry(device_time + TimeDelta::seconds(2), 'I', "Other", "later"));
+        assert_eq!(finished.len(), 1);
+        assert!(grouper.finish().is_none());
+    }
+}
@@ -0,0 +1,383 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use crate::coalesce::Coalescer;
+use crate::tui::LogEntry;
+use crate::DevInsightError;
+
+// The tag native crash dumps are logged under, so tombstones read from a bugreport go
+// through the same detector as live ones
+const TOMBSTONE_TAG: &str = "DEBUG";
+
+// dumpstate sections holding logcat output, and the buffer each one reads. SYSTEM LOG
+// mixes main, system and crash, so its entries only get a buffer from the dividers in it.
+const LOG_SECTIONS: &[(&str, Option<&str>)] = &[
+    ("SYSTEM LOG", None),
+    ("EVENT LOG", Some("events")),
+    ("RADIO LOG", Some("radio")),
+];
+
+// An Android bugreport, either the zip `adb bugreport` writes or the bare text file
+pub struct Bugreport {
+    pub fingerprint: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    text: String,
+    tombstones: Vec<String>,  // Tombstone files carried alongside the text in a zip
+}
+
+impl Bugreport {
+    pub fn open(path: &Path) -> Result<Self, DevInsightError> {
+        let error = |e: &dyn std::fmt::Display| DevInsightError::BugreportError(format!("{}: {}", path.display(), e));
+        let file = File::open(path).map_err(|e| error(&e))?;
+
+        let is_zip = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+        let (text, tombstones) = if is_zip {
+            read_zip(file).map_err(|e| error(&e))?
+        } else {
+            let mut bytes = Vec::new();
+            (&file).read_to_end(&mut bytes).map_err(|e| error(&e))?;
+            (String::from_utf8_lossy(&bytes).into_owned(), Vec::new())
+        };
+        if !text.contains("== dumpstate") {
+            return Err(error(&"not a bugreport (no dumpstate header)"));
+        }
+
+        let fingerprint = text.lines()
+            .find_map(|line| line.strip_prefix("Build fingerprint: "))
+            .map(|value| value.trim().trim_matches('\'').to_string());
+        let model = property(&text, "ro.product.model");
+        let serial = property(&text, "ro.serialno");
+        Ok(Self { fingerprint, model, serial, text, tombstones })
+    }
+
+    // "Pixel 7 (28011FDH2000B9) | google/panther/panther:14/UP1A.231005.007/10754064:user/release-keys"
+    pub fn header(&self) -> Option<String> {
+        let device = match (&self.model, &self.serial) {
+            (Some(model), Some(serial)) => Some(format!("{} ({})", model, serial)),
+            (model, serial) => model.clone().or_else(|| serial.clone()),
+        };
+        match (device, &self.fingerprint) {
+            (Some(device), Some(fingerprint)) => Some(format!("{} | {}", device, fingerprint)),
+            (Some(device), None) => Some(device),
+            (None, fingerprint) => fingerprint.clone(),
+        }
+    }
+
+    // The log sections in the order they appear, then the tombstones
+    pub fn entries(&self, on_entry: &mut dyn FnMut(LogEntry)) {
+        let taken_at = self.text.lines()
+            .find_map(|line| line.strip_prefix("== dumpstate: "))
+            .and_then(parse_time)
+            .unwrap_or_else(Local::now);
+
+        let lines: Vec<&str> = self.text.lines().collect();
+        let mut start = 0;
+        while start < lines.len() {
+            let Some(title) = section_title(lines[start]) else {
+                start += 1;
+                continue;
+            };
+            let end = lines[start + 1..]
+                .iter()
+                .position(|line| section_title(line).is_some())
+                .map_or(lines.len(), |offset| start + 1 + offset);
+            let body = &lines[start + 1..end];
+
+            if let Some(&(_, buffer)) = LOG_SECTIONS.iter().find(|(name, _)| title.starts_with(name)) {
+                let mut coalescer = buffer.map_or_else(Coalescer::new, Coalescer::with_buffer).read_at(taken_at);
+                for line in body {
+                    coalescer.push_line(line).into_iter().for_each(&mut *on_entry);
+                }
+                coalescer.flush().into_iter().for_each(&mut *on_entry);
+            } else if title.starts_with("TOMBSTONE") {
+                // Older dumpstate versions inline each tombstone as its own section
+                tombstone_entries(&body.join("\n"), taken_at, on_entry);
+            }
+            start = end;
+        }
+
+        for tombstone in &self.tombstones {
+            tombstone_entries(tombstone, taken_at, on_entry);
+        }
+    }
+}
+
+// A getprop value from the SYSTEM PROPERTIES section: "[ro.product.model]: [Pixel 7]"
+fn property(text: &str, name: &str) -> Option<String> {
+    let prefix = format!("[{}]: [", name);
+    text.lines()
+        .find_map(|line| line.strip_prefix(prefix.as_str()))
+        .map(|value| value.trim_end().trim_end_matches(']').to_string())
+}
+
+// "------ SYSTEM LOG (logcat -v threadtime -v printable -v uid -d *:v) ------" gives
+// "SYSTEM LOG (...)". The closing "------ 0.5s was the duration of 'SYSTEM LOG' ------"
+// counts as a title too so it ends the section.
+fn section_title(line: &str) -> Option<&str> {
+    let inner = line.strip_prefix("------ ")?.strip_suffix(" ------")?;
+    Some(inner.trim())
+}
+
+// The main text named by main_entry.txt (or the first bugreport-*.txt), plus any text
+// tombstones under FS/data/tombstones
+fn read_zip(file: File) -> Result<(String, Vec<String>), Box<dyn std::error::Error>> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    let read_entry = |archive: &mut zip::ZipArchive<File>, name: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let mut bytes = Vec::new();
+        archive.by_name(name)?.read_to_end(&mut bytes)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    };
+
+    let main_name = match read_entry(&mut archive, "main_entry.txt") {
+        Ok(name) => name.trim().to_string(),
+        Err(_) => archive.file_names()
+            .find(|name| name.starts_with("bugreport") && name.ends_with(".txt"))
+            .ok_or("no bugreport text in the zip")?
+            .to_string(),
+    };
+    let text = read_entry(&mut archive, &main_name)?;
+
+    let mut tombstone_names: Vec<String> = archive.file_names()
+        .filter(|name| name.starts_with("FS/data/tombstones/tombstone_") && !name.ends_with(".pb"))
+        .map(str::to_string)
+        .collect();
+    tombstone_names.sort();
+    let tombstones = tombstone_names
+        .iter()
+        .map(|name| read_entry(&mut archive, name))
+        .collect::<Result<_, _>>()?;
+    Ok((text, tombstones))
+}
+
+// Replay a tombstone as the DEBUG lines crash_dump would have logged, stamped with the
+// crash time it records ("Timestamp: 2024-03-20 09:58:00.123456789+0000")
+fn tombstone_entries(text: &str, fallback: DateTime<Local>, on_entry: &mut dyn FnMut(LogEntry)) {
+    let timestamp = text.lines()
+        .find_map(|line| line.strip_prefix("Timestamp: "))
+        .and_then(parse_time)
+        .unwrap_or(fallback);
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let mut entry = LogEntry::new(timestamp, 'F', TOMBSTONE_TAG, line);
+        entry.buffer = Some("crash".to_string());
+        on_entry(entry);
+    }
+}
+
+// Tombstone timestamps carry their offset ("2024-03-20 09:58:00.123456789+0000"); the
+// dumpstate header ("2024-03-20 10:00:00") is in the device's local time
+fn parse_time(text: &str) -> Option<DateTime<Local>> {
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f%z") {
+        return Some(time.with_timezone(&Local));
+    }
+    let naive = NaiveDateTime::parse_from_str(text.get(..19)?, "%Y-%m-%d %H:%M:%S").ok()?;
+    Local.from_local_datetime(&naive).single()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use chrono::Utc;
+
+    const REPORT: &str = "\
+========================================================
+== dumpstate: 2024-03-20 10:00:00
+========================================================
+Build fingerprint: 'google/panther/panther:14/UP1A.231005.007/10754064:user/release-keys'
+------ SYSTEM PROPERTIES (getprop) ------
+[ro.product.model]: [Pixel 7]
+[ro.serialno]: [28011FDH2000B9]
+------ 0.1s was the duration of 'SYSTEM PROPERTIES' ------
+------ SYSTEM LOG (logcat -v threadtime -v printable -v uid -d *:v) ------
+--------- beginning of main
+03-20 09:59:58.000  1000  1234  1234 I Main: main line
+--------- beginning of crash
+03-20 09:59:59.000 10123  4242  4242 E AndroidRuntime: FATAL EXCEPTION: main
+03-20 09:59:59.000 10123  4242  4242 E AndroidRuntime: Process: com.example.app, PID: 4242
+------ 0.5s was the duration of 'SYSTEM LOG' ------
+------ EVENT LOG (logcat -b events -v threadtime -v printable -v uid -d *:v) ------
+03-20 09:59:57.000  1000  1000  1100 I am_proc_start: [0,4242,10123,com.example.app,activity,{}]
+------ 0.1s was the duration of 'EVENT LOG' ------
+------ RADIO LOG (logcat -b radio -v threadtime -v printable -v uid -d *:v) ------
+03-20 09:59:56.000  1001  2000  2000 D RILJ: radio line
+------ 0.1s was the duration of 'RADIO LOG' ------
+------ DUMPSYS (/system/bin/dumpsys) ------
+03-20 09:59:55.000  1000  1000  1000 I NotALog: inside another section
+------ TOMBSTONE (/data/tombstones/tombstone_00) ------
+*** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***
+Timestamp: 2024-03-20 09:58:00.123456789+0000
+pid: 4343, tid: 4343, name: main  >>> com.example.native <<<
+";
+
+    const TOMBSTONE: &str = "\
+*** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***
+Timestamp: 2024-03-20 09:57:00.000000000+0000
+pid: 5151, tid: 5151, name: main  >>> com.example.zipped <<<
+";
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("devinsight-{}-{}", std::process::id(), name))
+    }
+
+    fn open_text(name: &str, text: &str) -> Result<Bugreport, DevInsightError> {
+        let path = temp_path(name);
+        std::fs::write(&path, text).unwrap();
+        let bugreport = Bugreport::open(&path);
+        std::fs::remove_file(&path).ok();
+        bugreport
+    }
+
+    fn open_zip(name: &str, files: &[(&str, &str)]) -> Bugreport {
+        let path = temp_path(name);
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        for (file, contents) in files {
+            writer.start_file(*file, zip::write::SimpleFileOptions::default()).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+        let bugreport = Bugreport::open(&path);
+        std::fs::remove_file(&path).ok();
+        bugreport.unwrap()
+    }
+
+    fn entries(bugreport: &Bugreport) -> Vec<LogEntry> {
+        let mut entries = Vec::new();
+        bugreport.entries(&mut |entry| entries.push(entry));
+        entries
+    }
+
+    fn summary(entries: &[LogEntry]) -> Vec<(String, Option<String>)> {
+        entries.iter().map(|entry| (entry.tag.clone(), entry.buffer.clone())).collect()
+    }
+
+    fn owned(pairs: &[(&str, Option<&str>)]) -> Vec<(String, Option<String>)> {
+        pairs.iter().map(|(tag, buffer)| (tag.to_string(), buffer.map(str::to_string))).collect()
+    }
+
+    #[test]
+    fn sections_are_split_and_tagged_with_their_buffer() {
+        let bugreport = open_text("sections.txt", REPORT).unwrap();
+        let entries = entries(&bugreport);
+        assert_eq!(summary(&entries), owned(&[
+            ("UNKNOWN", Some("main")),
+            ("Main", Some("main")),
+            ("UNKNOWN", Some("crash")),
+            ("AndroidRuntime", Some("crash")),
+            ("AndroidRuntime", Some("crash")),
+            ("am_proc_start", Some("events")),
+            ("RILJ", Some("radio")),
+            ("DEBUG", Some("crash")),
+            ("DEBUG", Some("crash")),
+            ("DEBUG", Some("crash")),
+        ]));
+        assert_eq!((entries[3].pid, entries[3].uid.as_deref()), (Some(4242), Some("10123")));
+    }
+
+    #[test]
+    fn system_log_without_dividers_has_no_buffer() {
+        let text = "\
+== dumpstate: 2024-03-20 10:00:00
+------ SYSTEM LOG (logcat -v threadtime -d *:v) ------
+03-20 09:59:58.000  1234  1234 I Main: main line
+------ 0.5s was the duration of 'SYSTEM LOG' ------
+";
+        let bugreport = open_text("nodividers.txt", text).unwrap();
+        assert_eq!(summary(&entries(&bugreport)), owned(&[("Main", None)]));
+    }
+
+    #[test]
+    fn log_lines_take_the_year_of_the_report() {
+        let text = "\
+== dumpstate: 2019-01-02 10:00:00
+------ SYSTEM LOG (logcat -v threadtime -d *:v) ------
+12-31 23:59:58.000  1234  1234 I Main: last year
+01-02 09:59:58.000  1234  1234 I Main: this year
+------ 0.5s was the duration of 'SYSTEM LOG' ------
+------ EVENT LOG (logcat -b events -v threadtime -d *:v) ------
+01-02 09:59:57.000  1000  1100 I am_proc_start: [0,4242,10123,com.example.app,activity,{}]
+------ 0.1s was the duration of 'EVENT LOG' ------
+";
+        let bugreport = open_text("year.txt", text).unwrap();
+        let times: Vec<String> = entries(&bugreport).iter()
+            .map(|entry| entry.timestamp.naive_local().to_string())
+            .collect();
+        assert_eq!(times, ["2018-12-31 23:59:58", "2019-01-02 09:59:58", "2019-01-02 09:59:57"]);
+    }
+
+    #[test]
+    fn inline_tombstones_use_their_own_timestamp() {
+        let bugreport = open_text("inline.txt", REPORT).unwrap();
+        let entries = entries(&bugreport);
+        let tombstone = entries.iter().find(|entry| entry.tag == TOMBSTONE_TAG).unwrap();
+        assert_eq!(tombstone.timestamp.with_timezone(&Utc).to_rfc3339(), "2024-03-20T09:58:00.123456789+00:00");
+    }
+
+    #[test]
+    fn header_has_model_serial_and_build() {
+        let bugreport = open_text("header.txt", REPORT).unwrap();
+        assert_eq!(bugreport.serial.as_deref(), Some("28011FDH2000B9"));
+        assert_eq!(
+            bugreport.header().as_deref(),
+            Some("Pixel 7 (28011FDH2000B9) | google/panther/panther:14/UP1A.231005.007/10754064:user/release-keys")
+        );
+
+        let bare = open_text("bare.txt", "== dumpstate: 2024-03-20 10:00:00\n").unwrap();
+        assert_eq!(bare.header(), None);
+    }
+
+    #[test]
+    fn text_without_dumpstate_is_rejected() {
+        let result = open_text("plain.txt", "03-20 09:59:58.000  1234  1234 I Main: just logcat\n");
+        assert!(matches!(result, Err(DevInsightError::BugreportError(_))));
+    }
+
+    #[test]
+    fn zip_reads_the_main_entry_and_tombstones() {
+        let bugreport = open_zip("entry.zip", &[
+            ("bugreport-decoy.txt", "not this one"),
+            ("main_entry.txt", "bugreport-panther-UP1A.txt\n"),
+            ("bugreport-panther-UP1A.txt", REPORT),
+            ("FS/data/tombstones/tombstone_01", TOMBSTONE),
+            ("FS/data/tombstones/tombstone_01.pb", "binary"),
+        ]);
+        assert_eq!(bugreport.model.as_deref(), Some("Pixel 7"));
+
+        let entries = entries(&bugreport);
+        let processes: Vec<&str> = entries.iter()
+            .filter(|entry| entry.tag == TOMBSTONE_TAG && entry.message.starts_with("pid: "))
+            .map(|entry| entry.message.as_str())
+            .collect();
+        assert_eq!(processes, [
+            "pid: 4343, tid: 4343, name: main  >>> com.example.native <<<",
+            "pid: 5151, tid: 5151, name: main  >>> com.example.zipped <<<",
+        ]);
+        assert!(!entries.iter().any(|entry| entry.message == "binary"));
+    }
+
+    #[test]
+    fn zip_without_main_entry_uses_the_bugreport_text() {
+        let bugreport = open_zip("noentry.zip", &[
+            ("version.txt", "2.0"),
+            ("bugreport-panther-UP1A.txt", REPORT),
+        ]);
+        assert_eq!(bugreport.serial.as_deref(), Some("28011FDH2000B9"));
+    }
+
+    #[test]
+    fn parse_time_applies_the_tombstone_offset() {
+        let time = parse_time("2024-03-20 09:58:00.123456789+0000").unwrap();
+        assert_eq!(time.with_timezone(&Utc).to_rfc3339(), "2024-03-20T09:58:00.123456789+00:00");
+
+        let time = parse_time("2024-03-20 09:58:00.5-0700").unwrap();
+        assert_eq!(time.with_timezone(&Utc).to_rfc3339(), "2024-03-20T16:58:00.500+00:00");
+    }
+
+    #[test]
+    fn parse_time_reads_the_dumpstate_header_as_local() {
+        let time = parse_time("2024-03-20 10:00:00").unwrap();
+        assert_eq!(time.naive_local().to_string(), "2024-03-20 10:00:00");
+    }
+}
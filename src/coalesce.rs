@@ -0,0 +1,219 @@
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+use chrono::{DateTime, Local};
+use crate::parser::{parse_header_at, parse_log_entry};
+use crate::tui::LogEntry;
+
+// How long a pending entry may wait for continuation lines once the stream goes quiet
+pub const IDLE_FLUSH: Duration = Duration::from_millis(100);
+
+// Joins lines without a logcat header (`-v long` bodies, wrapped messages, split JSON
+// payloads) onto the entry they belong to instead of emitting them as separate rows.
+pub struct Coalescer {
+    pending: Option<LogEntry>,
+    buffer: Option<String>,  // Buffer named by the most recent divider
+    read_at: Option<DateTime<Local>>,  // When the text was captured, if not just now
+}
+
+impl Coalescer {
+    pub fn new() -> Self {
+        Self { pending: None, buffer: None, read_at: None }
+    }
+
+    // For text known to come from one buffer before any divider says so
+    pub fn with_buffer(buffer: &str) -> Self {
+        Self { pending: None, buffer: Some(buffer.to_string()), read_at: None }
+    }
+
+    // For text captured earlier, so "MM-DD" timestamps get the year it was captured in
+    pub fn read_at(mut self, time: DateTime<Local>) -> Self {
+        self.read_at = Some(time);
+        self
+    }
+
+    // Feed one raw line and get back any entries that are now complete
+    pub fn push_line(&mut self, line: &str) -> Vec<LogEntry> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let mut complete = Vec::new();
+
+        if let Some(mut entry) = parse_header_at(line, self.read_at.unwrap_or_else(Local::now)) {
+            entry.buffer = self.buffer.clone();
+            complete.extend(self.pending.replace(entry));
+        } else if line.trim().is_empty() {
+            // `-v long` terminates each entry with a blank line
+            complete.extend(self.pending.take());
+        } else if line.starts_with("--------- ") {
+            // Buffer dividers ("--------- beginning of main", "--------- switch to events")
+            // stand on their own and tell us which buffer the following entries come from
+            complete.extend(self.pending.take());
+            let divider = line.trim_start_matches('-').trim();
+            if let Some(buffer) = divider.strip_prefix("beginning of ").or_else(|| divider.strip_prefix("switch to ")) {
+                self.buffer = Some(buffer.trim().to_string());
+            }
+            let mut entry = parse_log_entry(line);
+            entry.buffer = self.buffer.clone();
+            complete.push(entry);
+        } else if let Some(pending) = &mut self.pending {
+            if !pending.message.is_empty() {
+                pending.message.push('\n');
+            }
+            pending.message.push_str(line);
+        } else {
+            complete.push(parse_log_entry(line));
+        }
+
+        complete
+    }
+
+    pub fn flush(&mut self) -> Option<LogEntry> {
+        self.pending.take()
+    }
+}
+
+// Read lines on a helper thread and coalesce them into entries. The pending entry is
+// flushed once no line has arrived for IDLE_FLUSH, so live tailing isn't held back
+// waiting for the next header.
+pub fn coalesce_lines<R, F>(mut reader: R, mut on_entry: F) -> io::Result<()>
+where
+    R: BufRead + Send + 'static,
+    F: FnMut(LogEntry),
+{
+    let (line_tx, line_rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) => break,
+                Ok(_) => {
+                    // Device logs aren't guaranteed to be valid UTF-8
+                    let line = String::from_utf8_lossy(&buf).into_owned();
+                    if line_tx.send(Ok(line)).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    line_tx.send(Err(e)).ok();
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut coalescer = Coalescer::new();
+    loop {
+        match line_rx.recv_timeout(IDLE_FLUSH) {
+            Ok(Ok(line)) => coalescer.push_line(&line).into_iter().for_each(&mut on_entry),
+            Ok(Err(e)) => {
+                coalescer.flush().into_iter().for_each(&mut on_entry);
+                return Err(e);
+            }
+            Err(RecvTimeoutError::Timeout) => coalescer.flush().into_iter().for_each(&mut on_entry),
+            Err(RecvTimeoutError::Disconnected) => {
+                coalescer.flush().into_iter().for_each(&mut on_entry);
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Read};
+
+    fn push_all(coalescer: &mut Coalescer, lines: &[&str]) -> Vec<LogEntry> {
+        let mut entries: Vec<LogEntry> = lines.iter().flat_map(|line| coalescer.push_line(line)).collect();
+        entries.extend(coalescer.flush());
+        entries
+    }
+
+    fn messages(entries: &[LogEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.message.as_str()).collect()
+    }
+
+    // Hands out its chunks one read at a time, pausing before each
+    struct SlowReader {
+        chunks: Vec<(Duration, &'static str)>,
+    }
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.chunks.is_empty() {
+                return Ok(0);
+            }
+            let (delay, chunk) = self.chunks.remove(0);
+            std::thread::sleep(delay);
+            buf[..chunk.len()].copy_from_slice(chunk.as_bytes());
+            Ok(chunk.len())
+        }
+    }
+
+    fn coalesce(chunks: Vec<(Duration, &'static str)>) -> Vec<LogEntry> {
+        let mut entries = Vec::new();
+        coalesce_lines(BufReader::new(SlowReader { chunks }), |entry| entries.push(entry)).unwrap();
+        entries
+    }
+
+    #[test]
+    fn continuation_lines_join_the_pending_entry() {
+        let entries = push_all(&mut Coalescer::new(), &[
+            "03-21 10:23:45.678  1234  1234 I Foo: {\"a\": 1,\r\n",
+            "  \"b\": 2}\n",
+            "03-21 10:23:45.679  1234  1234 I Foo: next\n",
+        ]);
+        assert_eq!(messages(&entries), ["{\"a\": 1,\n  \"b\": 2}", "next"]);
+    }
+
+    #[test]
+    fn headerless_lines_stand_alone_before_any_entry() {
+        let entries = push_all(&mut Coalescer::new(), &["pasted text", "more text"]);
+        assert_eq!(messages(&entries), ["pasted text", "more text"]);
+        assert!(entries.iter().all(|entry| entry.priority == '?'));
+    }
+
+    #[test]
+    fn long_format_blocks_end_at_a_blank_line() {
+        let mut coalescer = Coalescer::new();
+        let mut entries = Vec::new();
+        for line in ["[ 03-21 10:23:45.678  1234: 1250 W/Foo ]", "first line", "second line", ""] {
+            entries.extend(coalescer.push_line(line));
+        }
+        assert_eq!(messages(&entries), ["first line\nsecond line"]);
+        assert_eq!((entries[0].pid, entries[0].tid, entries[0].tag.as_str()), (Some(1234), Some(1250), "Foo"));
+        assert!(coalescer.flush().is_none());
+    }
+
+    #[test]
+    fn dividers_stand_alone_and_set_the_buffer() {
+        let entries = push_all(&mut Coalescer::with_buffer("main"), &[
+            "03-21 10:23:45.678  1234  1234 I Foo: before",
+            "--------- switch to system",
+            "03-21 10:23:45.679  1234  1234 I Bar: after",
+            "wrapped",
+        ]);
+        assert_eq!(messages(&entries), ["before", "--------- switch to system", "after\nwrapped"]);
+        let buffers: Vec<Option<&str>> = entries.iter().map(|entry| entry.buffer.as_deref()).collect();
+        assert_eq!(buffers, [Some("main"), Some("system"), Some("system")]);
+    }
+
+    #[test]
+    fn pending_entry_flushes_when_the_stream_goes_quiet() {
+        let entries = coalesce(vec![
+            (Duration::ZERO, "03-21 10:23:45.678  1234  1234 I Foo: first\n"),
+            (IDLE_FLUSH * 5, "late\n"),
+        ]);
+        assert_eq!(messages(&entries), ["first", "late"]);
+        assert_eq!(entries[1].priority, '?');
+    }
+
+    #[test]
+    fn continuation_within_the_idle_window_is_joined() {
+        let entries = coalesce(vec![
+            (Duration::ZERO, "03-21 10:23:45.678  1234  1234 I Foo: first\n"),
+            (Duration::ZERO, "second\n"),
+        ]);
+        assert_eq!(messages(&entries), ["first\nsecond"]);
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+
+// What to show for a row pushed through a ContextWindow
+pub enum ContextRow<T> {
+    Separator,  // `--` between groups that aren't adjacent
+    Context(T),
+    Match(T),
+}
+
+// grep -A/-B/-C over a stream of rows: matches plus up to `before` rows ahead of each
+// and `after` rows behind it. Used on entries in standard mode and on log indices in
+// the TUI.
+pub struct ContextWindow<T> {
+    before: usize,
+    after: usize,
+    recent: VecDeque<T>,  // Non-matching rows since the last one shown, at most `before`
+    after_left: usize,  // Rows still to show after the last match
+    skipped: bool,  // A row was left out since the last one shown
+    shown_any: bool,
+}
+
+impl<T> ContextWindow<T> {
+    pub fn new(before: usize, after: usize) -> Self {
+        Self {
+            before,
+            after,
+            recent: VecDeque::with_capacity(before + 1),
+            after_left: 0,
+            skipped: false,
+            shown_any: false,
+        }
+    }
+
+    // The rows to show now, in order. Without any context there are no separators, as
+    // with grep.
+    pub fn push(&mut self, row: T, matched: bool) -> Vec<ContextRow<T>> {
+        let mut rows = Vec::new();
+        if matched {
+            if self.skipped && self.shown_any && (self.before > 0 || self.after > 0) {
+                rows.push(ContextRow::Separator);
+            }
+            rows.extend(self.recent.drain(..).map(ContextRow::Context));
+            rows.push(ContextRow::Match(row));
+            self.after_left = self.after;
+            self.skipped = false;
+            self.shown_any = true;
+        } else if self.after_left > 0 {
+            self.after_left -= 1;
+            rows.push(ContextRow::Context(row));
+        } else {
+            self.recent.push_back(row);
+            if self.recent.len() > self.before {
+                self.recent.pop_front();
+                self.skipped = true;
+            }
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Push rows 0.. through the window, matching the given ones, and render what it
+    // shows as "--", "c<row>" for context and "m<row>" for matches
+    fn show(before: usize, after: usize, rows: usize, matches: &[usize]) -> Vec<String> {
+        let mut window = ContextWindow::new(before, after);
+        (0..rows)
+            .flat_map(|row| window.push(row, matches.contains(&row)))
+            .map(|row| match row {
+                ContextRow::Separator => "--".to_string(),
+                ContextRow::Context(row) => format!("c{}", row),
+                ContextRow::Match(row) => format!("m{}", row),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn before_rows_lead_the_match() {
+        assert_eq!(show(2, 0, 6, &[4]), ["c2", "c3", "m4"]);
+        // Fewer rows than asked for at the start of the stream
+        assert_eq!(show(3, 0, 6, &[1]), ["c0", "m1"]);
+    }
+
+    #[test]
+    fn after_rows_follow_the_match() {
+        assert_eq!(show(0, 2, 6, &[1]), ["m1", "c2", "c3"]);
+        // Cut short by the end of the stream
+        assert_eq!(show(0, 3, 6, &[4]), ["m4", "c5"]);
+    }
+
+    #[test]
+    fn overlapping_windows_merge() {
+        assert_eq!(show(2, 2, 8, &[2, 5]), ["c0", "c1", "m2", "c3", "c4", "m5", "c6", "c7"]);
+        // A match inside the previous one's after rows restarts them
+        assert_eq!(show(1, 2, 8, &[1, 2]), ["c0", "m1", "m2", "c3", "c4"]);
+    }
+
+    #[test]
+    fn adjacent_windows_have_no_separator() {
+        assert_eq!(show(1, 1, 6, &[1, 4]), ["c0", "m1", "c2", "c3", "m4", "c5"]);
+    }
+
+    #[test]
+    fn separator_marks_skipped_rows() {
+        assert_eq!(show(1, 1, 9, &[1, 6]), ["c0", "m1", "c2", "--", "c5", "m6", "c7"]);
+        // Nothing shown yet, so rows skipped before the first match get no separator
+        assert_eq!(show(1, 0, 6, &[4]), ["c3", "m4"]);
+    }
+
+    #[test]
+    fn no_separator_without_context() {
+        assert_eq!(show(0, 0, 6, &[1, 4]), ["m1", "m4"]);
+    }
+}
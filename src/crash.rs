@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local};
+use crate::block::{Block, BlockGrouper};
+use crate::tui::LogEntry;
+use crate::tombstone::{NativeCrash, TombstoneDetector};
+use crate::anr::{Anr, AnrDetector};
+
+pub const CRASH_TAG: &str = "AndroidRuntime";
+
+// A crash is considered complete once its lines stop arriving for this long
+const CRASH_IDLE: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+pub struct StackFrame {
+    pub class: String,
+    pub method: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl StackFrame {
+    // "com.example.Foo.bar(Foo.java:42)", with or without the leading "at "
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().strip_prefix("at ").unwrap_or(text.trim());
+        let open = text.find('(')?;
+        let location = text[open + 1..].strip_suffix(')')?;
+        let (class, method) = text[..open].rsplit_once('.')?;
+
+        let (file, line) = match location.rsplit_once(':') {
+            Some((file, line)) if line.parse::<u32>().is_ok() => (file, line.parse().ok()),
+            _ => (location, None),
+        };
+
+        Some(Self {
+            class: class.to_string(),
+            method: method.to_string(),
+            file: (!file.is_empty()).then(|| file.to_string()),
+            line,
+        })
+    }
+}
+
+impl std::fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}.{}(", self.class, self.method)?;
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{})", file, line),
+            (Some(file), None) => write!(f, "{})", file),
+            (None, Some(line)) => write!(f, "Unknown Source:{})", line),
+            (None, None) => write!(f, "Unknown Source)"),
+        }
+    }
+}
+
+pub struct Cause {
+    pub exception: String,
+    pub message: String,
+    pub frames: Vec<StackFrame>,
+}
+
+pub struct Crash {
+    pub timestamp: DateTime<Local>,
+    pub process: String,
+    pub pid: Option<u32>,
+    pub thread: String,
+    pub exception: String,
+    pub message: String,
+    pub frames: Vec<StackFrame>,
+    pub causes: Vec<Cause>,
+}
+
+impl Crash {
+    fn new(timestamp: DateTime<Local>, thread: &str) -> Self {
+        Self {
+            timestamp,
+            process: String::new(),
+            pid: None,
+            thread: thread.trim().to_string(),
+            exception: String::new(),
+            message: String::new(),
+            frames: Vec::new(),
+            causes: Vec::new(),
+        }
+    }
+
+    // The innermost cause is usually where the bug actually is
+    pub fn root_exception(&self) -> &str {
+        self.causes.last().map_or(&self.exception, |c| &c.exception)
+    }
+
+    // Render the crash back into the familiar Java stack trace layout
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "FATAL EXCEPTION: {}\nProcess: {}, PID: {}\n",
+            self.thread,
+            self.process,
+            self.pid.map(|p| p.to_string()).unwrap_or_else(|| "?".to_string())
+        );
+        report.push_str(&exception_line(&self.exception, &self.message));
+        for frame in &self.frames {
+            report.push_str(&format!("\n\t{}", frame));
+        }
+        for cause in &self.causes {
+            report.push_str(&format!("\nCaused by: {}", exception_line(&cause.exception, &cause.message)));
+            for frame in &cause.frames {
+                report.push_str(&format!("\n\t{}", frame));
+            }
+        }
+        report
+    }
+
+}
+
+impl Block for Crash {
+    // Apply one line of the trace, returning false if it doesn't belong to this crash
+    fn apply_line(&mut self, line: &str) -> bool {
+        let trimmed = line.trim();
+
+        if let Some(frame) = trimmed.strip_prefix("at ").and_then(StackFrame::parse) {
+            match self.causes.last_mut() {
+                Some(cause) => cause.frames.push(frame),
+                None => self.frames.push(frame),
+            }
+            return true;
+        }
+
+        if trimmed.starts_with("... ") && trimmed.ends_with(" more") {
+            return true;
+        }
+
+        if let Some(cause) = trimmed.strip_prefix("Caused by: ") {
+            let (exception, message) = split_exception(cause);
+            self.causes.push(Cause { exception, message, frames: Vec::new() });
+            return true;
+        }
+
+        if let Some(process) = trimmed.strip_prefix("Process: ") {
+            let (process, pid) = process.split_once(", PID: ").unwrap_or((process, ""));
+            self.process = process.to_string();
+            self.pid = pid.trim().parse().ok();
+            return true;
+        }
+
+        if self.exception.is_empty() {
+            (self.exception, self.message) = split_exception(trimmed);
+            return true;
+        }
+
+        // Multi-line exception messages continue until the first frame
+        let (message, frames) = match self.causes.last_mut() {
+            Some(cause) => (&mut cause.message, &cause.frames),
+            None => (&mut self.message, &self.frames),
+        };
+        if frames.is_empty() {
+            message.push('\n');
+            message.push_str(line);
+            return true;
+        }
+
+        false
+    }
+}
+
+// Everything listed in the Crashes view
+pub enum CrashRecord {
+    Java(Crash),
+    Native(NativeCrash),
+    Anr(Anr),
+}
+
+impl CrashRecord {
+    // One-line summary for the crash list
+    pub fn summary(&self) -> String {
+        match self {
+            CrashRecord::Java(crash) => format!(
+                "💥 {} {} {}",
+                crash.timestamp.format("%H:%M:%S"),
+                crash.process,
+                crash.root_exception().rsplit('.').next().unwrap_or_default()
+            ),
+            CrashRecord::Native(crash) => format!(
+                "🧨 {} {} {}",
+                crash.timestamp.format("%H:%M:%S"),
+                crash.process,
+                crash.signal
+            ),
+            CrashRecord::Anr(anr) => format!(
+                "⏳ {} {} ANR",
+                anr.timestamp.format("%H:%M:%S"),
+                anr.package
+            ),
+        }
+    }
+
+    pub fn report(&self) -> String {
+        match self {
+            CrashRecord::Java(crash) => crash.report(),
+            CrashRecord::Native(crash) => crash.report(),
+            CrashRecord::Anr(anr) => anr.report(),
+        }
+    }
+}
+
+// The Java crash, tombstone and ANR detectors for one device
+struct DeviceDetectors {
+    java: CrashDetector,
+    native: TombstoneDetector,
+    anr: AnrDetector,
+}
+
+impl DeviceDetectors {
+    fn new() -> Self {
+        Self {
+            java: CrashDetector::new(),
+            native: TombstoneDetector::new(),
+            anr: AnrDetector::new(),
+        }
+    }
+}
+
+// Runs the Java crash, tombstone and ANR detectors side by side over the parsed stream.
+// Each device gets its own set so interleaved streams don't break up each other's blocks.
+pub struct CrashMonitor {
+    devices: HashMap<Option<String>, DeviceDetectors>,
+}
+
+impl CrashMonitor {
+    pub fn new() -> Self {
+        Self {
+            devices: HashMap::new(),
+        }
+    }
+
+    pub fn feed(&mut self, entry: &LogEntry) -> Vec<CrashRecord> {
+        let detectors = self.devices.entry(entry.device.clone()).or_insert_with(DeviceDetectors::new);
+        let mut records: Vec<CrashRecord> = detectors.java.feed(entry).into_iter().map(CrashRecord::Java).collect();
+        records.extend(detectors.native.feed(entry).into_iter().map(CrashRecord::Native));
+        records.extend(detectors.anr.feed(entry).into_iter().map(CrashRecord::Anr));
+        records
+    }
+
+    pub fn flush_stale(&mut self) -> Vec<CrashRecord> {
+        let mut records = Vec::new();
+        for detectors in self.devices.values_mut() {
+            records.extend(detectors.java.flush_stale().map(CrashRecord::Java));
+            records.extend(detectors.native.flush_stale().map(CrashRecord::Native));
+            records.extend(detectors.anr.flush_stale().map(CrashRecord::Anr));
+        }
+        records
+    }
+
+    pub fn finish(&mut self) -> Vec<CrashRecord> {
+        let mut records = Vec::new();
+        for detectors in self.devices.values_mut() {
+            records.extend(detectors.java.finish().map(CrashRecord::Java));
+            records.extend(detectors.native.finish().map(CrashRecord::Native));
+            records.extend(detectors.anr.finish().map(CrashRecord::Anr));
+        }
+        records
+    }
+}
+
+fn exception_line(exception: &str, message: &str) -> String {
+    if message.is_empty() {
+        exception.to_string()
+    } else {
+        format!("{}: {}", exception, message)
+    }
+}
+
+fn split_exception(text: &str) -> (String, String) {
+    match text.split_once(": ") {
+        Some((exception, message)) => (exception.trim().to_string(), message.to_string()),
+        None => (text.trim().to_string(), String::new()),
+    }
+}
+
+// Groups the AndroidRuntime "FATAL EXCEPTION" block that follows an uncaught exception
+// into a single Crash record.
+pub struct CrashDetector {
+    blocks: BlockGrouper<Crash>,
+}
+
+impl CrashDetector {
+    pub fn new() -> Self {
+        Self { blocks: BlockGrouper::new(CRASH_IDLE) }
+    }
+
+    pub fn feed(&mut self, entry: &LogEntry) -> Vec<Crash> {
+        self.blocks.feed(entry, CRASH_TAG, |line| {
+            line.strip_prefix("FATAL EXCEPTION: ").map(|thread| Crash::new(entry.timestamp, thread))
+        })
+    }
+
+    pub fn flush_stale(&mut self) -> Option<Crash> {
+        self.blocks.flush_stale(Instant::now())
+    }
+
+    pub fn finish(&mut self) -> Option<Crash> {
+        self.blocks.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACE: &[&str] = &[
+        "FATAL EXCEPTION: main",
+        "Process: com.example.app, PID: 4242",
+        "java.lang.RuntimeException: Unable to start activity",
+        "second line of the message",
+        "\tat android.app.ActivityThread.performLaunchActivity(ActivityThread.java:3645)",
+        "\tat android.os.Looper.loop(Looper.java:288)",
+        "Caused by: java.lang.IllegalStateException: boom",
+        "\tat com.example.app.Main.onCreate(Main.kt:42)",
+        "\tat android.app.Activity.performCreate(Unknown Source:8)",
+        "\t... 12 more",
+    ];
+
+    fn entry(pid: u32, tag: &str, message: &str) -> LogEntry {
+        let mut entry = LogEntry::new(Local::now(), 'E', tag, message);
+        entry.pid = Some(pid);
+        entry
+    }
+
+    #[test]
+    fn stack_frame_parse() {
+        let frame = StackFrame::parse("  at com.example.Foo$Inner.bar(Foo.kt:42)").unwrap();
+        assert_eq!((frame.class.as_str(), frame.method.as_str()), ("com.example.Foo$Inner", "bar"));
+        assert_eq!((frame.file.as_deref(), frame.line), (Some("Foo.kt"), Some(42)));
+
+        let frame = StackFrame::parse("a.b.c(Unknown Source)").unwrap();
+        assert_eq!((frame.file.as_deref(), frame.line), (Some("Unknown Source"), None));
+        let frame = StackFrame::parse("a.b.c(Native Method)").unwrap();
+        assert_eq!(frame.to_string(), "at a.b.c(Native Method)");
+        let frame = StackFrame::parse("a.b.c()").unwrap();
+        assert_eq!(frame.to_string(), "at a.b.c(Unknown Source)");
+
+        assert!(StackFrame::parse("not a frame").is_none());
+        assert!(StackFrame::parse("noclass(Foo.java:1)").is_none());
+    }
+
+    #[test]
+    fn trace_with_causes_is_one_crash() {
+        let mut detector = CrashDetector::new();
+        let mut finished: Vec<Crash> = TRACE.iter().flat_map(|line| detector.feed(&entry(4242, CRASH_TAG, line))).collect();
+        finished.extend(detector.finish());
+
+        assert_eq!(finished.len(), 1);
+        let crash = &finished[0];
+        assert_eq!((crash.thread.as_str(), crash.process.as_str(), crash.pid), ("main", "com.example.app", Some(4242)));
+        assert_eq!(crash.exception, "java.lang.RuntimeException");
+        assert_eq!(crash.message, "Unable to start activity\nsecond line of the message");
+        assert_eq!(crash.frames.len(), 2);
+        assert_eq!(crash.causes.len(), 1);
+        assert_eq!(crash.causes[0].exception, "java.lang.IllegalStateException");
+        assert_eq!(crash.causes[0].frames.len(), 2);
+        assert_eq!(crash.root_exception(), "java.lang.IllegalStateException");
+        assert!(crash.report().contains("\nCaused by: java.lang.IllegalStateException: boom\n\tat com.example.app.Main.onCreate(Main.kt:42)"));
+    }
+
+    #[test]
+    fn trace_in_one_entry() {
+        let mut detector = CrashDetector::new();
+        assert!(detector.feed(&entry(4242, CRASH_TAG, &TRACE.join("\n"))).is_empty());
+        let crash = detector.finish().unwrap();
+        assert_eq!((crash.frames.len(), crash.causes.len()), (2, 1));
+    }
+
+    #[test]
+    fn interleaved_pid_ends_the_crash() {
+        let mut detector = CrashDetector::new();
+        let mut finished = Vec::new();
+        for line in &TRACE[..5] {
+            finished.extend(detector.feed(&entry(4242, CRASH_TAG, line)));
+        }
+        finished.extend(detector.feed(&entry(5000, CRASH_TAG, "\tat com.other.App.run(App.java:1)")));
+
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].frames.len(), 1);
+        assert!(detector.finish().is_none());
+    }
+
+    #[test]
+    fn other_tags_pass_through() {
+        let mut detector = CrashDetector::new();
+        detector.feed(&entry(4242, CRASH_TAG, TRACE[0]));
+        assert!(detector.feed(&entry(4242, "Other", "\tat a.b.c(D.java:1)")).is_empty());
+        detector.feed(&entry(4242, CRASH_TAG, TRACE[2]));
+        let crash = detector.finish().unwrap();
+        assert_eq!((crash.exception.as_str(), crash.frames.len()), ("java.lang.RuntimeException", 0));
+    }
+
+    #[test]
+    fn new_crash_finishes_the_previous_one() {
+        let mut detector = CrashDetector::new();
+        detector.feed(&entry(4242, CRASH_TAG, TRACE[0]));
+        let finished = detector.feed(&entry(4242, CRASH_TAG, "FATAL EXCEPTION: worker"));
+        assert_eq!(finished[0].thread, "main");
+        assert_eq!(detector.finish().unwrap().thread, "worker");
+    }
+
+    #[test]
+    fn flush_stale_waits_for_the_idle_time() {
+        let mut detector = CrashDetector::new();
+        detector.feed(&entry(4242, CRASH_TAG, TRACE[0]));
+        assert!(detector.flush_stale().is_none());
+        assert!(detector.blocks.flush_stale(Instant::now() + CRASH_IDLE / 2).is_none());
+        assert_eq!(detector.blocks.flush_stale(Instant::now() + CRASH_IDLE).unwrap().thread, "main");
+        assert!(detector.finish().is_none());
+    }
+}
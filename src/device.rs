@@ -0,0 +1,327 @@
+use std::io::{BufRead, BufReader, Cursor, Read};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use crate::adb_client::AdbClient;
+use crate::parser::parse_header;
+use crate::DevInsightError;
+
+// One line of `adb devices -l`:
+// "emulator-5554  device product:sdk_gphone64_arm64 model:sdk_gphone64_arm64 device:emu64a transport_id:1"
+pub struct Device {
+    pub serial: String,
+    pub state: String,  // "device" once it's usable; "offline", "unauthorized", ...
+    pub model: Option<String>,
+}
+
+impl Device {
+    fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let serial = parts.next()?.to_string();
+        let state = parts.next()?.to_string();
+        let model = parts
+            .find_map(|part| part.strip_prefix("model:"))
+            .map(str::to_string);
+        Some(Self { serial, state, model })
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.state == "device"
+    }
+}
+
+// How DevInsight reaches devices: by running the adb binary, or by talking to the adb
+// server directly over its socket
+#[derive(Clone)]
+pub enum AdbBackend {
+    Binary,
+    Native(AdbClient),
+}
+
+impl AdbBackend {
+    pub fn list_devices(&self) -> Result<Vec<Device>, DevInsightError> {
+        match self {
+            AdbBackend::Binary => {
+                let output = Command::new("adb")
+                    .args(["devices", "-l"])
+                    .output()
+                    .map_err(|_| DevInsightError::AdbNotFound)?;
+                Ok(parse_devices(&String::from_utf8_lossy(&output.stdout)))
+            }
+            AdbBackend::Native(client) => client.devices(),
+        }
+    }
+
+    // "device", "offline", ... for the device; an error if adb can't see it at all
+    pub fn get_state(&self, serial: Option<&str>) -> Result<String, DevInsightError> {
+        match self {
+            AdbBackend::Binary => {
+                let output = adb_command(serial)
+                    .arg("get-state")
+                    .output()
+                    .map_err(|_| DevInsightError::AdbNotFound)?;
+                if !output.status.success() {
+                    return Err(DevInsightError::DeviceError(
+                        String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                    ));
+                }
+                Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            }
+            AdbBackend::Native(client) => client.get_state(serial),
+        }
+    }
+
+    // Run a shell command on the device and collect its output
+    pub fn shell(&self, serial: Option<&str>, args: &[&str]) -> Result<String, DevInsightError> {
+        let command = shell_command(args);
+        match self {
+            AdbBackend::Binary => {
+                let output = adb_command(serial)
+                    .args(["shell", &command])
+                    .output()
+                    .map_err(|_| DevInsightError::AdbNotFound)?;
+                if !output.status.success() {
+                    return Err(DevInsightError::DeviceError(format!(
+                        "'{}' failed: {}",
+                        command,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+            }
+            AdbBackend::Native(client) => {
+                let mut output = String::new();
+                client.shell(serial, &command)?.read_to_string(&mut output)?;
+                Ok(output)
+            }
+        }
+    }
+
+    // Start logcat with the given arguments, returning its output and a handle for
+    // checking how it ended
+    pub fn logcat(
+        &self,
+        serial: Option<&str>,
+        args: &[String],
+    ) -> Result<(Box<dyn BufRead + Send>, LogcatProcess), DevInsightError> {
+        match self {
+            AdbBackend::Binary => {
+                let mut child = adb_command(serial)
+                    .arg("logcat")
+                    .args(args)
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()
+                    .map_err(|_| DevInsightError::AdbNotFound)?;
+                let stdout = child.stdout.take()
+                    .ok_or(DevInsightError::LogcatCaptureFailed("Failed to capture stdout".to_string()))?;
+                Ok((Box::new(BufReader::new(stdout)), LogcatProcess { child: Some(child), error: None }))
+            }
+            AdbBackend::Native(client) => {
+                let mut command = vec!["logcat"];
+                command.extend(args.iter().map(String::as_str));
+                let mut stream = client.shell(serial, &shell_command(&command))?;
+
+                // The shell stream carries logcat's complaints in-band, so check the first
+                // line before passing anything on
+                let mut first = String::new();
+                stream.read_line(&mut first)?;
+                let error = if first.is_empty() {
+                    Some("the device closed the shell before logcat started".to_string())
+                } else if !is_raw_format(args) && !is_logcat_line(&first) {
+                    let mut rest = String::new();
+                    (&mut stream).take(ERROR_TEXT_LIMIT).read_to_string(&mut rest).ok();
+                    Some(format!("{}{}", first, rest).trim().to_string())
+                } else {
+                    None
+                };
+
+                let output: Box<dyn BufRead + Send> = match error {
+                    Some(_) => Box::new(std::io::empty()),
+                    None => Box::new(Cursor::new(first).chain(stream)),
+                };
+                Ok((output, LogcatProcess { child: None, error }))
+            }
+        }
+    }
+
+    // Block until the device is back
+    pub fn wait_for_device(&self, serial: Option<&str>) -> Result<(), DevInsightError> {
+        match self {
+            AdbBackend::Binary => {
+                let status = adb_command(serial)
+                    .arg("wait-for-device")
+                    .status()
+                    .map_err(|_| DevInsightError::AdbNotFound)?;
+                if !status.success() {
+                    return Err(DevInsightError::DeviceError(format!(
+                        "wait-for-device failed for {}",
+                        serial.unwrap_or("the default device")
+                    )));
+                }
+                Ok(())
+            }
+            // The server's own wait-for service holds the connection open with no timeout,
+            // so poll instead; a restarting server or a device still booting both just
+            // mean another round
+            AdbBackend::Native(client) => loop {
+                if matches!(client.get_state(serial).as_deref(), Ok("device")) {
+                    return Ok(());
+                }
+                std::thread::sleep(Duration::from_secs(1));
+            },
+        }
+    }
+}
+
+// How a logcat started by AdbBackend::logcat ended
+pub struct LogcatProcess {
+    child: Option<Child>,
+    error: Option<String>,  // What the socket backend got instead of logcat output
+}
+
+impl LogcatProcess {
+    // Call once the output is exhausted. The socket backend has no exit status, so it
+    // fails when the shell gave something other than logcat output, or nothing at all.
+    pub fn wait(self) -> Result<(), DevInsightError> {
+        if let Some(error) = self.error {
+            return Err(DevInsightError::LogcatCaptureFailed(error));
+        }
+        let Some(mut child) = self.child else {
+            return Ok(());
+        };
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            pipe.read_to_string(&mut stderr).ok();
+        }
+        if !child.wait()?.success() {
+            return Err(DevInsightError::LogcatCaptureFailed(stderr.trim().to_string()));
+        }
+        Ok(())
+    }
+}
+
+// How much of a failed logcat's output to keep for the error message
+const ERROR_TEXT_LIMIT: u64 = 4096;
+
+// `-v raw` lines have no header, so there's nothing to tell them from an error by
+fn is_raw_format(args: &[String]) -> bool {
+    args.windows(2)
+        .any(|pair| pair[0] == "-v" && pair[1].split([',', ' ']).any(|format| format == "raw"))
+}
+
+fn is_logcat_line(line: &str) -> bool {
+    line.starts_with("--------- ") || parse_header(line.trim_end()).is_some()
+}
+
+// An adb command aimed at one device, or at adb's default device when serial is None
+fn adb_command(serial: Option<&str>) -> Command {
+    let mut command = Command::new("adb");
+    if let Some(serial) = serial {
+        command.args(["-s", serial]);
+    }
+    command
+}
+
+// Join arguments into one command line for the device shell, quoting any that need it
+fn shell_command(args: &[&str]) -> String {
+    args.iter()
+        .map(|arg| {
+            let plain = !arg.is_empty()
+                && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c));
+            if plain {
+                arg.to_string()
+            } else {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn parse_devices(text: &str) -> Vec<Device> {
+    text.lines()
+        .filter(|line| !line.starts_with("List of devices") && !line.starts_with('*'))
+        .filter_map(Device::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb_client::tests::serve;
+
+    // Start logcat over a fake server whose shell answers with `output`
+    fn native_logcat(args: &[&str], output: &str) -> (String, Result<(), DevInsightError>) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut command = vec!["logcat"];
+        command.extend(args.iter().map(String::as_str));
+        let service = format!("shell:{}", shell_command(&command));
+        let (client, server) = serve(vec![vec![
+            ("host:transport:emulator-5554", b"OKAY".to_vec()),
+            (&service, format!("OKAY{}", output).into_bytes()),
+        ]]);
+
+        let (mut stream, process) = AdbBackend::Native(client).logcat(Some("emulator-5554"), &args).unwrap();
+        let mut text = String::new();
+        stream.read_to_string(&mut text).unwrap();
+        server.join().unwrap();
+        (text, process.wait())
+    }
+
+    #[test]
+    fn native_logcat_passes_output_through() {
+        let output = "--------- beginning of main\n03-21 10:23:45.678  1234  1234 I Foo: one\n";
+        let (text, result) = native_logcat(&["-v", "threadtime"], output);
+        assert_eq!(text, output);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn native_logcat_reports_error_text() {
+        let (text, result) = native_logcat(&["-v", "bogus"], "Unknown format 'bogus'\nUsage: logcat [options]\n");
+        assert_eq!(text, "");
+        assert!(matches!(result, Err(DevInsightError::LogcatCaptureFailed(message)) if message.starts_with("Unknown format 'bogus'")));
+    }
+
+    #[test]
+    fn native_logcat_reports_early_eof() {
+        let (text, result) = native_logcat(&["-v", "brief"], "");
+        assert_eq!(text, "");
+        assert!(matches!(result, Err(DevInsightError::LogcatCaptureFailed(_))));
+    }
+
+    #[test]
+    fn native_logcat_accepts_raw_output() {
+        let (text, result) = native_logcat(&["-v", "raw"], "just a message\n");
+        assert_eq!(text, "just a message\n");
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn parse_devices_reads_states_and_attributes() {
+        let devices = parse_devices("\
+* daemon not running; starting now at tcp:5037
+* daemon started successfully
+List of devices attached
+emulator-5554          device product:sdk_gphone64_arm64 model:sdk_gphone64_arm64 device:emu64a transport_id:1
+R58M12345AB            unauthorized usb:1-2 transport_id:4
+192.168.1.20:5555      offline transport_id:5
+28011FDH2000B9         device
+
+");
+        let rows: Vec<(&str, &str, Option<&str>, bool)> = devices.iter()
+            .map(|d| (d.serial.as_str(), d.state.as_str(), d.model.as_deref(), d.is_online()))
+            .collect();
+        assert_eq!(rows, [
+            ("emulator-5554", "device", Some("sdk_gphone64_arm64"), true),
+            ("R58M12345AB", "unauthorized", None, false),
+            ("192.168.1.20:5555", "offline", None, false),
+            ("28011FDH2000B9", "device", None, true),
+        ]);
+    }
+
+    #[test]
+    fn shell_command_quotes_arguments() {
+        assert_eq!(shell_command(&["logcat", "-T", "03-21 10:23:45.678", "it's"]), "logcat -T '03-21 10:23:45.678' 'it'\\''s'");
+    }
+}
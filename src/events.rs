@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+use crate::device::AdbBackend;
+use crate::tui::LogEntry;
+use crate::DevInsightError;
+
+const EVENTS_BUFFER: &str = "events";
+const DEVICE_TAGS_PATH: &str = "/system/etc/event-log-tags";
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EventValue {
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<EventValue>),
+}
+
+impl std::fmt::Display for EventValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventValue::Int(value) => write!(f, "{}", value),
+            EventValue::Float(value) => write!(f, "{}", value),
+            EventValue::String(value) => write!(f, "{}", value),
+            EventValue::List(values) => {
+                let items: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", items.join(","))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EventField {
+    pub name: String,
+    pub value: EventValue,
+}
+
+// Value types from the event-log-tags "(name|type|unit)" descriptions
+#[derive(Clone, Copy)]
+enum FieldType {
+    Int,
+    Long,
+    String,
+    List,
+    Float,
+}
+
+impl FieldType {
+    fn from_code(code: &str) -> Option<Self> {
+        match code.trim() {
+            "1" => Some(FieldType::Int),
+            "2" => Some(FieldType::Long),
+            "3" => Some(FieldType::String),
+            "4" => Some(FieldType::List),
+            "5" => Some(FieldType::Float),
+            _ => None,
+        }
+    }
+
+    fn convert(self, raw: &str) -> EventValue {
+        let raw = raw.trim();
+        let typed = match self {
+            FieldType::Int | FieldType::Long => raw.parse().ok().map(EventValue::Int),
+            FieldType::Float => raw.parse().ok().map(EventValue::Float),
+            FieldType::List => raw.strip_prefix('[').and_then(|r| r.strip_suffix(']')).map(|inner| {
+                EventValue::List(split_values(inner).into_iter().map(guess_value).collect())
+            }),
+            FieldType::String => None,
+        };
+        typed.unwrap_or_else(|| EventValue::String(raw.to_string()))
+    }
+}
+
+struct EventTag {
+    name: String,
+    fields: Vec<(String, FieldType)>,
+}
+
+// Decodes events-buffer entries ("am_proc_start: [0,1234,10100,com.example,...]", or
+// "30014: [...]" when the device couldn't name the tag) into named, typed fields using
+// the tag descriptions from an event-log-tags file.
+pub struct EventTags {
+    tags: HashMap<u32, EventTag>,
+    numbers: HashMap<String, u32>,  // Tag name -> number
+}
+
+impl EventTags {
+    pub fn load(path: &Path) -> Result<Self, DevInsightError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| DevInsightError::EventTagsError(format!("{}: {}", path.display(), e)))?;
+        Ok(Self::parse(&text))
+    }
+
+    // Read the tag descriptions the device itself uses. Returns None if no device is
+    // reachable or the file is missing.
+    pub fn pull_from_device(backend: &AdbBackend, serial: Option<&str>) -> Option<Self> {
+        let output = backend.shell(serial, &["cat", DEVICE_TAGS_PATH]).ok()?;
+        let tags = Self::parse(&output);
+        (!tags.tags.is_empty()).then_some(tags)
+    }
+
+    // "30014 am_proc_start (User|1|5),(PID|1|5),(UID|1|5),(Process Name|3),(Type|3),(Component|3)"
+    pub fn parse(text: &str) -> Self {
+        let mut tags = HashMap::new();
+        let mut numbers = HashMap::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(3, char::is_whitespace);
+            let (Some(number), Some(name)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            let Ok(number) = number.parse::<u32>() else {
+                continue;
+            };
+
+            let fields = parts
+                .next()
+                .unwrap_or("")
+                .split("),")
+                .filter_map(|field| {
+                    let field = field.trim().trim_start_matches('(').trim_end_matches(')');
+                    let mut parts = field.split('|');
+                    let name = parts.next()?.trim();
+                    let field_type = FieldType::from_code(parts.next()?)?;
+                    Some((name.to_string(), field_type))
+                })
+                .collect();
+
+            numbers.insert(name.to_string(), number);
+            tags.insert(number, EventTag { name: name.to_string(), fields });
+        }
+
+        Self { tags, numbers }
+    }
+
+    // Name the tag and split its payload into fields. Entries from other buffers, or
+    // with tags the file doesn't describe, are left untouched. Without a buffer divider
+    // to go by, only a "[...]" payload marks an entry as an event. Entries that already
+    // carry fields (e.g. from a session saved with decoding) are left as they are.
+    pub fn decode_entry(&self, entry: &mut LogEntry) {
+        if !entry.fields.is_empty() {
+            return;
+        }
+        let is_event = match entry.buffer.as_deref() {
+            Some(buffer) => buffer == EVENTS_BUFFER,
+            None => entry.message.trim().starts_with('[') && entry.message.trim().ends_with(']'),
+        };
+        if !is_event {
+            return;
+        }
+        let number = match entry.tag.parse::<u32>() {
+            Ok(number) => number,
+            Err(_) => match self.numbers.get(&entry.tag) {
+                Some(&number) => number,
+                None => return,
+            },
+        };
+        let Some(tag) = self.tags.get(&number) else {
+            return;
+        };
+        entry.tag = tag.name.clone();
+        if tag.fields.is_empty() {
+            return;
+        }
+
+        let payload = entry.message.trim();
+        let raw_values = match payload.strip_prefix('[').and_then(|p| p.strip_suffix(']')) {
+            Some(inner) if tag.fields.len() > 1 => split_values(inner),
+            _ => vec![payload],
+        };
+
+        // Commas inside string values produce extra parts. Give them back to the free-text
+        // field (a message or reason) if there is one, otherwise to the last string field.
+        let mut values: Vec<String> = raw_values.iter().map(|v| v.to_string()).collect();
+        if values.len() > tag.fields.len() {
+            let surplus = values.len() - tag.fields.len();
+            let is_string = |field_type: &FieldType| matches!(field_type, FieldType::String);
+            let target = tag.fields
+                .iter()
+                .position(|(name, field_type)| is_string(field_type) && is_free_text(name))
+                .or_else(|| tag.fields.iter().rposition(|(_, field_type)| is_string(field_type)))
+                .unwrap_or(tag.fields.len() - 1);
+            let joined = values.drain(target..=target + surplus).collect::<Vec<_>>().join(",");
+            values.insert(target, joined);
+        }
+
+        entry.fields = tag.fields
+            .iter()
+            .zip(values)
+            .map(|((name, field_type), raw)| EventField {
+                name: name.clone(),
+                value: field_type.convert(&raw),
+            })
+            .collect();
+        entry.message = entry.fields
+            .iter()
+            .map(|field| format!("{}={}", field.name, field.value))
+            .collect::<Vec<_>>()
+            .join(", ");
+    }
+}
+
+fn is_free_text(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.contains("message") || name.contains("reason") || name == "msg"
+}
+
+// Split a list body on top-level commas, leaving nested "[...]" lists whole
+fn split_values(text: &str) -> Vec<&str> {
+    let mut values = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                values.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    values.push(&text[start..]);
+    values
+}
+
+// Nested list items carry no type information, so take them as numbers where possible
+fn guess_value(raw: &str) -> EventValue {
+    let raw = raw.trim();
+    if let Ok(value) = raw.parse() {
+        EventValue::Int(value)
+    } else if let Ok(value) = raw.parse() {
+        EventValue::Float(value)
+    } else {
+        EventValue::String(raw.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    const TAGS: &str = "\
+# event-log-tags
+42 answer (to life the universe etc|3)
+2722 battery_level (level|1|6),(voltage|1|1),(temperature|1|1)
+30014 am_proc_start (User|1|5),(PID|1|5),(UID|1|5),(Process Name|3),(Type|3),(Component|3)
+30039 am_crash (User|1|5),(PID|1|5),(Process Name|3),(Flags|1|5),(Exception|3),(Message|3),(File|3),(Line|1|5),(Recoverable|1|5)
+30100 am_notes (Note|3),(Tags|3)
+40000 list_tag (items|4)
+not_a_number bogus (x|1)
+31000 no_fields
+";
+
+    fn tags() -> EventTags {
+        EventTags::parse(TAGS)
+    }
+
+    fn event(tag: &str, message: &str, buffer: Option<&str>) -> LogEntry {
+        let mut entry = LogEntry::new(Local::now(), 'I', tag, message);
+        entry.buffer = buffer.map(str::to_string);
+        entry
+    }
+
+    fn decode(tag: &str, message: &str, buffer: Option<&str>) -> LogEntry {
+        let mut entry = event(tag, message, buffer);
+        tags().decode_entry(&mut entry);
+        entry
+    }
+
+    fn field(entry: &LogEntry, name: &str) -> String {
+        entry.field(name).map(ToString::to_string).unwrap_or_else(|| panic!("no field {}", name))
+    }
+
+    #[test]
+    fn parse_reads_tags_and_fields() {
+        let tags = tags();
+        assert_eq!(tags.tags.len(), 7);
+        assert_eq!(tags.numbers["am_proc_start"], 30014);
+        let fields: Vec<&str> = tags.tags[&30014].fields.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(fields, ["User", "PID", "UID", "Process Name", "Type", "Component"]);
+        assert_eq!(tags.tags[&42].fields[0].0, "to life the universe etc");
+        assert!(tags.tags[&31000].fields.is_empty());
+        assert!(!tags.numbers.contains_key("bogus"));
+    }
+
+    #[test]
+    fn decode_by_name_and_number() {
+        let entry = decode("am_proc_start", "[0,4242,10123,com.example.app,activity,{com.example.app/.Main}]", Some("events"));
+        assert_eq!(field(&entry, "PID"), "4242");
+        assert_eq!(field(&entry, "Process Name"), "com.example.app");
+        assert!(matches!(entry.field("UID"), Some(EventValue::Int(10123))));
+        assert!(entry.message.starts_with("User=0, PID=4242, UID=10123, Process Name=com.example.app"));
+
+        let entry = decode("2722", "[85,4123,250]", Some("events"));
+        assert_eq!(entry.tag, "battery_level");
+        assert_eq!(field(&entry, "level"), "85");
+    }
+
+    #[test]
+    fn single_field_takes_the_whole_payload() {
+        let entry = decode("answer", "forty, two", Some("events"));
+        assert_eq!(field(&entry, "to life the universe etc"), "forty, two");
+    }
+
+    #[test]
+    fn surplus_commas_go_to_the_free_text_field() {
+        let entry = decode(
+            "am_crash",
+            "[0,4242,com.example.app,952745540,java.lang.IllegalStateException,boom, with commas, here,Main.kt,42,0]",
+            Some("events"),
+        );
+        assert_eq!(field(&entry, "Message"), "boom, with commas, here");
+        assert_eq!(field(&entry, "File"), "Main.kt");
+        assert_eq!(field(&entry, "Line"), "42");
+    }
+
+    #[test]
+    fn surplus_commas_go_to_the_last_string_without_free_text() {
+        let entry = decode("am_notes", "[first,a,b,c]", Some("events"));
+        assert_eq!(field(&entry, "Note"), "first");
+        assert_eq!(field(&entry, "Tags"), "a,b,c");
+    }
+
+    #[test]
+    fn nested_lists_stay_whole() {
+        let entry = decode("list_tag", "[1,[2,3],x]", Some("events"));
+        match entry.field("items") {
+            Some(EventValue::List(items)) => assert_eq!(items.len(), 3),
+            _ => panic!("items isn't a list"),
+        }
+    }
+
+    #[test]
+    fn other_buffers_are_left_alone() {
+        let entry = decode("answer", "42", Some("main"));
+        assert_eq!((entry.tag.as_str(), entry.fields.len()), ("answer", 0));
+        let entry = decode("unknown_tag", "[1,2]", Some("events"));
+        assert_eq!((entry.tag.as_str(), entry.message.as_str()), ("unknown_tag", "[1,2]"));
+    }
+
+    #[test]
+    fn decoding_twice_changes_nothing() {
+        let mut entry = decode("am_proc_start", "[0,4242,10123,com.example.app,activity,{com.example.app/.Main}]", Some("events"));
+        let message = entry.message.clone();
+        tags().decode_entry(&mut entry);
+        assert_eq!(entry.message, message);
+        assert_eq!((entry.tag.as_str(), entry.fields.len()), ("am_proc_start", 6));
+        assert_eq!(field(&entry, "User"), "0");
+        assert_eq!(field(&entry, "PID"), "4242");
+    }
+
+    #[test]
+    fn unknown_buffer_needs_a_list_payload() {
+        let entry = decode("battery_level", "charging normally", None);
+        assert_eq!((entry.message.as_str(), entry.fields.len()), ("charging normally", 0));
+        let entry = decode("battery_level", "[85,4123,250]", None);
+        assert_eq!(field(&entry, "voltage"), "4123");
+    }
+}
@@ -0,0 +1,143 @@
+use crate::tui::{LogEntry, LogLevel};
+use crate::DevInsightError;
+
+// logcat's own filter syntax, e.g. `ActivityManager:I MyApp:V *:S`. Each `tag:level` shows
+// that tag at or above the level; `*:level` sets the level for every other tag (V if it
+// isn't given). A bare `tag` means `tag:V`, and a later spec for a tag overrides an
+// earlier one, as in logcat.
+#[derive(Clone)]
+pub struct FilterSpecs {
+    specs: Vec<(String, LogLevel)>,
+    default: LogLevel,
+}
+
+impl FilterSpecs {
+    pub fn parse<S: AsRef<str>>(specs: &[S]) -> Result<Self, DevInsightError> {
+        let mut filter = Self { specs: Vec::new(), default: LogLevel::Verbose };
+        for spec in specs.iter().flat_map(|spec| spec.as_ref().split_whitespace()) {
+            let (tag, level) = match spec.rsplit_once(':') {
+                Some((tag, level)) => (tag, parse_level(spec, level)?),
+                None => (spec, LogLevel::Verbose),
+            };
+            if tag.is_empty() {
+                return Err(DevInsightError::FilterSpecError(format!("'{}' has no tag", spec)));
+            }
+            if tag == "*" {
+                filter.default = level;
+            } else {
+                filter.specs.retain(|(existing, _)| existing != tag);
+                filter.specs.push((tag.to_string(), level));
+            }
+        }
+        Ok(filter)
+    }
+
+    // Entries without a logcat header have no tag, so they fall under `*`
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        let minimum = self.specs
+            .iter()
+            .find(|(tag, _)| *tag == entry.tag)
+            .map_or(self.default, |(_, level)| *level);
+        minimum != LogLevel::Silent && (entry.priority == '?' || entry.level >= minimum)
+    }
+
+    // The specs as `adb logcat` arguments, for filtering on the device. logcat doesn't take
+    // A, so assert goes down as F; the looser device filter is narrowed again locally.
+    pub fn logcat_args(&self) -> Vec<String> {
+        self.specs
+            .iter()
+            .map(|(tag, level)| (tag.as_str(), *level))
+            .chain(std::iter::once(("*", self.default)))
+            .map(|(tag, level)| {
+                let priority = if level == LogLevel::Assert { 'F' } else { level.priority() };
+                format!("{}:{}", tag, priority)
+            })
+            .collect()
+    }
+
+    // "ActivityManager:I MyApp:V *:S"
+    pub fn as_str(&self) -> String {
+        self.specs
+            .iter()
+            .map(|(tag, level)| format!("{}:{}", tag, level.priority()))
+            .chain(std::iter::once(format!("*:{}", self.default.priority())))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// logcat takes the priority letters V D I W E F S (any case); A is accepted for assert
+fn parse_level(spec: &str, level: &str) -> Result<LogLevel, DevInsightError> {
+    let mut letters = level.chars();
+    match (letters.next(), letters.next()) {
+        (Some(letter), None) if LogLevel::from_priority(letter.to_ascii_uppercase()) != LogLevel::Unknown => {
+            Ok(LogLevel::from_priority(letter.to_ascii_uppercase()))
+        }
+        _ => Err(DevInsightError::FilterSpecError(format!(
+            "'{}': unknown level '{}' (expected one of V D I W E F S)",
+            spec, level
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    fn entry(priority: char, tag: &str) -> LogEntry {
+        LogEntry::new(Local::now(), priority, tag, "message")
+    }
+
+    fn specs(specs: &[&str]) -> FilterSpecs {
+        FilterSpecs::parse(specs).unwrap()
+    }
+
+    #[test]
+    fn parse_reads_tags_levels_and_default() {
+        assert_eq!(specs(&["ActivityManager:I", "MyApp:v", "*:S"]).as_str(), "ActivityManager:I MyApp:V *:S");
+        assert_eq!(specs(&["MyApp"]).as_str(), "MyApp:V *:V");
+        assert_eq!(specs(&["ActivityManager:I MyApp:D"]).as_str(), "ActivityManager:I MyApp:D *:V");
+        assert_eq!(specs(&["MyApp:D", "Other:W", "MyApp:E"]).as_str(), "Other:W MyApp:E *:V");
+    }
+
+    #[test]
+    fn parse_rejects_bad_specs() {
+        assert!(FilterSpecs::parse(&[":D"]).is_err());
+        assert!(FilterSpecs::parse(&["MyApp:X"]).is_err());
+        assert!(FilterSpecs::parse(&["MyApp:DE"]).is_err());
+        assert!(FilterSpecs::parse(&["MyApp:"]).is_err());
+    }
+
+    #[test]
+    fn silent_default_hides_other_tags() {
+        let specs = specs(&["MyApp:D", "*:S"]);
+        assert!(specs.matches(&entry('D', "MyApp")));
+        assert!(!specs.matches(&entry('V', "MyApp")));
+        assert!(!specs.matches(&entry('F', "Other")));
+    }
+
+    #[test]
+    fn tag_level_takes_precedence_over_default() {
+        let specs = specs(&["Chatty:E", "Quiet:S", "*:D"]);
+        assert!(!specs.matches(&entry('W', "Chatty")));
+        assert!(specs.matches(&entry('E', "Chatty")));
+        assert!(!specs.matches(&entry('F', "Quiet")));
+        assert!(specs.matches(&entry('D', "Other")));
+        assert!(!specs.matches(&entry('V', "Other")));
+    }
+
+    #[test]
+    fn unparsed_lines_follow_the_default() {
+        let unparsed = entry('?', "");
+        assert!(specs(&["MyApp:E"]).matches(&unparsed));
+        assert!(!specs(&["MyApp:E", "*:S"]).matches(&unparsed));
+    }
+
+    #[test]
+    fn logcat_args_send_assert_as_fatal() {
+        assert_eq!(specs(&["MyApp:A", "*:S"]).logcat_args(), ["MyApp:F", "*:S"]);
+        assert_eq!(specs(&["MyApp:I", "*:A"]).logcat_args(), ["MyApp:I", "*:F"]);
+        assert_eq!(specs(&["MyApp:A"]).as_str(), "MyApp:A *:V");
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use crate::tui::LogEntry;
+
+// Under the sample policy, one in this many overflowing entries is let in
+const SAMPLE_EVERY: u64 = 10;
+
+// How often lines/sec is recomputed
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+// What to do when the reader produces entries faster than the TUI takes them
+#[derive(Clone, Copy, PartialEq)]
+pub enum OverflowPolicy {
+    Block,       // Stall the reader (and so logcat) until there's room; nothing is lost here
+    DropOldest,  // Make room by discarding the oldest queued entry
+    Sample,      // Keep one in SAMPLE_EVERY new entries, each replacing the oldest
+}
+
+impl OverflowPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "block" => Some(OverflowPolicy::Block),
+            "drop-oldest" => Some(OverflowPolicy::DropOldest),
+            "sample" => Some(OverflowPolicy::Sample),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverflowPolicy::Block => "block",
+            OverflowPolicy::DropOldest => "drop-oldest",
+            OverflowPolicy::Sample => "sample",
+        }
+    }
+}
+
+struct Shared {
+    queue: Mutex<VecDeque<LogEntry>>,
+    not_full: Condvar,
+    capacity: usize,
+    policy: OverflowPolicy,
+    received: AtomicU64,
+    dropped: AtomicU64,
+    parse_failures: AtomicU64,
+}
+
+// A bounded queue between the reader thread and the TUI that counts what it loses
+pub fn channel(capacity: usize, policy: OverflowPolicy) -> (IngestSender, IngestReceiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::with_capacity(capacity)),
+        not_full: Condvar::new(),
+        capacity: capacity.max(1),
+        policy,
+        received: AtomicU64::new(0),
+        dropped: AtomicU64::new(0),
+        parse_failures: AtomicU64::new(0),
+    });
+    let receiver = IngestReceiver {
+        shared: shared.clone(),
+        window_start: Instant::now(),
+        window_received: 0,
+        lines_per_sec: 0.0,
+    };
+    (IngestSender { shared, overflowed: 0 }, receiver)
+}
+
+pub struct IngestSender {
+    shared: Arc<Shared>,
+    overflowed: u64,  // Entries that arrived to a full queue, for sampling
+}
+
+impl IngestSender {
+    pub fn send(&mut self, entry: LogEntry) {
+        let shared = &*self.shared;
+        shared.received.fetch_add(1, Ordering::Relaxed);
+        // Lines without a logcat header, other than buffer dividers
+        if entry.priority == '?' && !entry.message.starts_with("--------- ") {
+            shared.parse_failures.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut queue = shared.queue.lock().unwrap();
+        if queue.len() >= shared.capacity {
+            match shared.policy {
+                OverflowPolicy::Block => {
+                    queue = shared.not_full
+                        .wait_while(queue, |queue| queue.len() >= shared.capacity)
+                        .unwrap();
+                }
+                OverflowPolicy::DropOldest => {
+                    queue.pop_front();
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                OverflowPolicy::Sample => {
+                    self.overflowed += 1;
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                    if !self.overflowed.is_multiple_of(SAMPLE_EVERY) {
+                        return;
+                    }
+                    queue.pop_front();
+                }
+            }
+        }
+        queue.push_back(entry);
+    }
+}
+
+pub struct IngestReceiver {
+    shared: Arc<Shared>,
+    window_start: Instant,
+    window_received: u64,  // `received` at window_start
+    lines_per_sec: f64,
+}
+
+impl IngestReceiver {
+    pub fn try_recv(&self) -> Option<LogEntry> {
+        let entry = self.shared.queue.lock().unwrap().pop_front();
+        self.shared.not_full.notify_all();
+        entry
+    }
+
+    // Everything queued so far
+    pub fn drain(&self) -> Vec<LogEntry> {
+        let entries: Vec<LogEntry> = self.shared.queue.lock().unwrap().drain(..).collect();
+        self.shared.not_full.notify_all();
+        entries
+    }
+
+    pub fn health(&mut self) -> IngestHealth {
+        self.health_at(Instant::now())
+    }
+
+    fn health_at(&mut self, now: Instant) -> IngestHealth {
+        let received = self.shared.received.load(Ordering::Relaxed);
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= RATE_WINDOW {
+            self.lines_per_sec = (received - self.window_received) as f64 / elapsed.as_secs_f64();
+            self.window_start = now;
+            self.window_received = received;
+        }
+        IngestHealth {
+            lines_per_sec: self.lines_per_sec,
+            dropped: self.shared.dropped.load(Ordering::Relaxed),
+            parse_failures: self.shared.parse_failures.load(Ordering::Relaxed),
+            queued: self.shared.queue.lock().unwrap().len(),
+            capacity: self.shared.capacity,
+            policy: self.shared.policy,
+        }
+    }
+}
+
+// A snapshot of the queue for the status bar and Stats view
+pub struct IngestHealth {
+    pub lines_per_sec: f64,
+    pub dropped: u64,
+    pub parse_failures: u64,
+    pub queued: usize,
+    pub capacity: usize,
+    pub policy: OverflowPolicy,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    fn entry(n: usize) -> LogEntry {
+        LogEntry::new(Local::now(), 'I', "Test", &n.to_string())
+    }
+
+    fn fill(policy: OverflowPolicy, count: usize) -> (IngestSender, IngestReceiver) {
+        let (mut sender, receiver) = channel(4, policy);
+        for n in 0..count {
+            sender.send(entry(n));
+        }
+        (sender, receiver)
+    }
+
+    fn messages(entries: Vec<LogEntry>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.message).collect()
+    }
+
+    #[test]
+    fn drop_oldest_keeps_the_newest() {
+        let (_sender, mut receiver) = fill(OverflowPolicy::DropOldest, 7);
+        let health = receiver.health();
+        assert_eq!((health.dropped, health.queued, health.capacity), (3, 4, 4));
+        assert_eq!(messages(receiver.drain()), ["3", "4", "5", "6"]);
+    }
+
+    #[test]
+    fn sample_lets_one_in_n_through() {
+        let (_sender, mut receiver) = fill(OverflowPolicy::Sample, 4 + 25);
+        // Overflowing entries 10 and 20 of 25 get in, each pushing out the oldest
+        assert_eq!(receiver.health().dropped, 25);
+        assert_eq!(messages(receiver.drain()), ["2", "3", "13", "23"]);
+    }
+
+    #[test]
+    fn sample_below_capacity_keeps_everything() {
+        let (_sender, mut receiver) = fill(OverflowPolicy::Sample, 4);
+        assert_eq!(receiver.health().dropped, 0);
+        assert_eq!(messages(receiver.drain()), ["0", "1", "2", "3"]);
+    }
+
+    #[test]
+    fn block_loses_nothing() {
+        let (sender, receiver) = channel(4, OverflowPolicy::Block);
+        let reader = std::thread::spawn(move || {
+            let mut sender = sender;
+            for n in 0..20 {
+                sender.send(entry(n));
+            }
+        });
+        let mut received = Vec::new();
+        while received.len() < 20 {
+            received.extend(receiver.try_recv());
+            assert!(receiver.shared.queue.lock().unwrap().len() <= 4);
+        }
+        reader.join().unwrap();
+        let expected: Vec<String> = (0..20).map(|n| n.to_string()).collect();
+        assert_eq!(messages(received), expected);
+        assert_eq!(receiver.shared.dropped.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn parse_failures_skip_dividers() {
+        let (mut sender, mut receiver) = channel(4, OverflowPolicy::DropOldest);
+        sender.send(LogEntry::new(Local::now(), '?', "UNKNOWN", "garbage"));
+        sender.send(LogEntry::new(Local::now(), '?', "UNKNOWN", "--------- beginning of main"));
+        sender.send(entry(0));
+        assert_eq!(receiver.health().parse_failures, 1);
+    }
+
+    #[test]
+    fn lines_per_sec_over_the_window() {
+        let (mut sender, mut receiver) = channel(100, OverflowPolicy::DropOldest);
+        let start = receiver.window_start;
+        for n in 0..30 {
+            sender.send(entry(n));
+        }
+        assert_eq!(receiver.health_at(start + RATE_WINDOW / 2).lines_per_sec, 0.0);
+        assert_eq!(receiver.health_at(start + RATE_WINDOW * 2).lines_per_sec, 15.0);
+
+        for n in 0..10 {
+            sender.send(entry(n));
+        }
+        // Only what arrived since the last window counts
+        assert_eq!(receiver.health_at(start + RATE_WINDOW * 3).lines_per_sec, 10.0);
+    }
+}
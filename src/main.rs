@@ -1,14 +1,54 @@
-use std::process::{Command, Stdio};
-use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
 use thiserror::Error;
 use colored::*;
 use clap::Parser;
 use std::path::PathBuf;
+use chrono::NaiveDateTime;
 mod tui;
 use tui::{Tui, LogEntry, LogLevel};
-use chrono::Local;
 mod storage;
 use storage::{LogStorage, StoredLog};
+mod parser;
+mod coalesce;
+mod block;
+mod crash;
+use crash::{CrashMonitor, CrashRecord};
+mod tombstone;
+mod anr;
+mod retrace;
+use retrace::Retracer;
+mod symbolize;
+use symbolize::Symbolizer;
+mod events;
+use events::EventTags;
+mod source;
+mod replay;
+use replay::{Pacer, Replay};
+use source::{LogSource, AdbSource, FileSource, SessionSource, StdinSource, CommandSource, MultiSource, BugreportSource};
+mod device;
+mod reconnect;
+mod adb_client;
+mod package;
+mod bugreport;
+mod ingest;
+mod query;
+use query::Query;
+mod filterspec;
+use filterspec::FilterSpecs;
+mod context;
+use context::{ContextRow, ContextWindow};
+use ingest::OverflowPolicy;
+use package::PackageFilter;
+use device::{AdbBackend, Device};
+use adb_client::AdbClient;
+
+// Entries read ahead of the printer in standard mode before the source waits
+const STANDARD_QUEUE: usize = 1024;
+
+// How often standard mode checks for finished incidents while no entries arrive
+const STALE_POLL: Duration = Duration::from_millis(250);
 
 #[derive(Error, Debug)]
 pub enum DevInsightError {
@@ -24,6 +64,28 @@ pub enum DevInsightError {
     StorageError(String),
     #[error("JSON serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
+    #[error("Mapping file error: {0}")]
+    MappingError(String),
+    #[error("Symbol directory error: {0}")]
+    SymbolError(String),
+    #[error("Event tags error: {0}")]
+    EventTagsError(String),
+    #[error("Device error: {0}")]
+    DeviceError(String),
+    #[error("Device not found: {0}")]
+    DeviceNotFound(String),
+    #[error("Device offline: {0}")]
+    DeviceOffline(String),
+    #[error("Device unauthorized (accept the USB debugging prompt): {0}")]
+    DeviceUnauthorized(String),
+    #[error("ADB server error: {0}")]
+    AdbServerError(String),
+    #[error("Bugreport error: {0}")]
+    BugreportError(String),
+    #[error("Invalid query: {0}")]
+    QueryError(String),
+    #[error("Invalid filterspec: {0}")]
+    FilterSpecError(String),
 }
 
 #[derive(Parser, Debug)]
@@ -38,17 +100,41 @@ struct Cli {
     #[arg(short, long, help = "Filter logs by specific tag")]
     tag: Option<String>,
     
+    #[arg(short = 'q', long = "query", help = "Filter expression, e.g. 'level>=W && tag~/^Net/ && !msg:\"heartbeat\"'")]
+    query: Option<String>,
+
+    #[arg(value_name = "FILTERSPEC", help = "logcat filterspecs, e.g. 'ActivityManager:I MyApp:V *:S'")]
+    filterspecs: Vec<String>,
+
+    #[arg(long = "push-filterspecs", help = "Also pass the filterspecs to logcat so filtered lines never leave the device (single device only; crash detection only sees what passes)")]
+    push_filterspecs: bool,
+
+    #[arg(short = 'A', long = "after-context", value_name = "NUM", help = "Also show NUM entries after each match")]
+    after_context: Option<usize>,
+
+    #[arg(short = 'B', long = "before-context", value_name = "NUM", help = "Also show NUM entries before each match")]
+    before_context: Option<usize>,
+
+    #[arg(short = 'C', long = "context", value_name = "NUM", help = "Also show NUM entries before and after each match")]
+    context: Option<usize>,
+
+    #[arg(short = 'p', long = "package", help = "Show only this app's processes, following it across restarts")]
+    package: Option<String>,
+
+    #[arg(long = "package-crashes", help = "With --package, also show system_server and tombstone lines about the app (deaths, ANRs, native crash dumps)", requires = "package")]
+    package_crashes: bool,
+
     #[arg(short = 'c', long, help = "Clear logs before starting")]
     clear: bool,
     
     #[arg(short = 'T', long, help = "Show logs from specific timestamp (format: 'YYYY-MM-DD HH:MM:SS')")]
     since: Option<String>,
     
-    #[arg(short = 'b', long = "buffer", help = "Select buffer (main, system, crash)", value_parser = ["main", "system", "crash"], default_value = "main")]
+    #[arg(short = 'b', long = "buffer", help = "Select buffer (main, system, crash, events, radio, all)", value_parser = ["main", "system", "crash", "events", "radio", "all"], default_value = "all")]
     buffer: String,
     
-    #[arg(short = 'v', long = "format", help = "Log format (brief, process, tag, thread, raw)", value_parser = ["brief", "process", "tag", "thread", "raw"], default_value = "brief")]
-    format: String,
+    #[arg(short = 'v', long = "format", help = "Log format (brief, process, tag, thread, raw, time, threadtime, long) [default: brief, threadtime with -i]", value_parser = ["brief", "process", "tag", "thread", "raw", "time", "threadtime", "long"])]
+    format: Option<String>,
     
     #[arg(short = 'i', long = "interactive", help = "Use interactive TUI mode")]
     interactive: bool,
@@ -61,56 +147,208 @@ struct Cli {
     
     #[arg(long = "max-size", help = "Maximum log file size in MB before rotation", default_value = "100")]
     max_size: u64,
+
+    #[arg(long = "queue-size", help = "Entries buffered between the reader and the TUI", default_value = "10000")]
+    queue_size: usize,
+
+    #[arg(long = "overflow", help = "What to do when the TUI falls behind: block the reader, drop the oldest queued entries, or sample 1 in 10", value_parser = ["block", "drop-oldest", "sample"], default_value = "block")]
+    overflow: String,
     
-    #[arg(long = "load", help = "Load and analyze logs from file")]
+    #[arg(long = "load", help = "Load and analyze logs saved with --save (a .jsonl file or a directory of them)", conflicts_with_all = ["file", "stdin", "exec", "bugreport"])]
     load: Option<PathBuf>,
+
+    #[arg(long = "mapping", help = "R8/ProGuard mapping.txt used to deobfuscate stack traces")]
+    mapping: Option<PathBuf>,
+
+    #[arg(long = "symbols", help = "Directory of unstripped .so files used to symbolicate native crashes")]
+    symbols: Option<PathBuf>,
+
+    #[arg(long = "event-tags", help = "event-log-tags file used to decode the events buffer (pulled from the device if omitted and --buffer includes events)")]
+    event_tags: Option<PathBuf>,
+
+    #[arg(short = 's', long = "serial", help = "Capture from this device serial (repeatable)", conflicts_with = "all_devices")]
+    serial: Vec<String>,
+
+    #[arg(long = "all-devices", help = "Capture from every attached device")]
+    all_devices: bool,
+
+    #[arg(long = "replay", help = "Replay the --load session with its original timing", requires = "load")]
+    replay: bool,
+
+    #[arg(long = "speed", help = "Replay speed multiplier (e.g. 0.5, 2, 10)", default_value = "1", value_parser = parse_speed)]
+    speed: f64,
+
+    #[arg(long = "file", help = "Read logs from a file (saved JSONL or logcat text) instead of adb", conflicts_with_all = ["stdin", "exec", "bugreport"])]
+    file: Option<PathBuf>,
+
+    #[arg(long = "bugreport", help = "Import the logs and tombstones of an Android bugreport (.zip or .txt)", conflicts_with_all = ["stdin", "exec"])]
+    bugreport: Option<PathBuf>,
+
+    #[arg(long = "stdin", help = "Read logcat text from standard input", conflicts_with = "exec")]
+    stdin: bool,
+
+    #[arg(long = "exec", help = "Read logcat text from the output of a shell command")]
+    exec: Option<String>,
+
+    #[arg(long = "adb-backend", help = "Reach devices by running the adb binary or by talking to the adb server socket directly", value_parser = ["binary", "native"], default_value = "binary")]
+    adb_backend: String,
+
+    #[arg(long = "adb-server", help = "adb server address for the native backend (default 127.0.0.1:5037, or $ANDROID_ADB_SERVER_PORT)")]
+    adb_server: Option<String>,
 }
 
-struct LogProcessor {
-    filter_level: Option<String>,
-    filter_tag: Option<String>,
+fn parse_speed(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(format!("'{}' is not a positive number", value)),
+    }
 }
 
-impl LogProcessor {
-    fn new(filter_level: Option<String>, filter_tag: Option<String>) -> Self {
-        Self {
-            filter_level,
-            filter_tag,
-        }
+impl Cli {
+    // Whether logs come from a live device rather than a file, pipe or command
+    fn uses_adb(&self) -> bool {
+        self.load.is_none() && self.file.is_none() && self.bugreport.is_none() && !self.stdin && self.exec.is_none()
+    }
+
+    // Whether the selected buffers include the events buffer
+    fn reads_events(&self) -> bool {
+        self.buffer == "events" || self.buffer == "all"
     }
 
-    fn should_process_log(&self, log: &str) -> bool {
-        if let Some(level) = &self.filter_level {
-            let level_pattern = format!(" {}/", level); // Brief format
-            let alt_pattern = format!("/{} ", level);   // Tag format
-            if !log.contains(&level_pattern) && !log.contains(&alt_pattern) {
-                return false;
+    fn log_format(&self) -> &str {
+        self.format.as_deref().unwrap_or(if self.interactive { "threadtime" } else { "brief" })
+    }
+
+    // The `adb logcat` arguments for the options given. Without --since the TUI starts
+    // with the last 50 entries so it isn't empty on a quiet device.
+    fn logcat_args(&self) -> Result<Vec<String>, DevInsightError> {
+        let mut args = vec![
+            "-b".to_string(), self.buffer.clone(),
+            "-v".to_string(), self.log_format().to_string(),
+        ];
+        match &self.since {
+            Some(since) => {
+                let since = NaiveDateTime::parse_from_str(since, "%Y-%m-%d %H:%M:%S%.f").map_err(|_| {
+                    DevInsightError::TimestampError(format!("'{}' (expected 'YYYY-MM-DD HH:MM:SS')", since))
+                })?;
+                args.push("-T".to_string());
+                args.push(since.format("%Y-%m-%d %H:%M:%S%.3f").to_string());
+            }
+            None if self.interactive => {
+                args.push("-T".to_string());
+                args.push("50".to_string());
             }
+            None => {}
         }
+        Ok(args)
+    }
 
-        if let Some(tag) = &self.filter_tag {
-            if !log.contains(tag) {
-                return false;
-            }
+    // --filter as a level, by name ("error") or letter ("E")
+    fn level_filter(&self) -> Result<Option<LogLevel>, DevInsightError> {
+        self.filter
+            .as_deref()
+            .map(|level| {
+                LogLevel::from_name(level)
+                    .ok_or_else(|| DevInsightError::QueryError(format!("unknown level '{}' for --filter", level)))
+            })
+            .transpose()
+    }
+
+    // --filter, --tag and --query combined into one filter
+    fn combined_query(&self) -> Result<Option<Query>, DevInsightError> {
+        let mut queries = Vec::new();
+        if let Some(level) = self.level_filter()? {
+            queries.push(Query::parse(&format!("level=={}", level.priority()))?);
+        }
+        queries.extend(self.tag_query()?);
+        Ok(Query::all(queries))
+    }
+
+    // --tag and --query combined. The TUI takes this as its filter expression and --filter
+    // as a level toggle, so it can be switched off there.
+    fn tag_query(&self) -> Result<Option<Query>, DevInsightError> {
+        let mut queries = Vec::new();
+        if let Some(tag) = &self.tag {
+            queries.push(Query::parse(&format!("tag:\"{}\"", tag.replace('"', "\\\"")))?);
+        }
+        if let Some(query) = &self.query {
+            queries.push(Query::parse(query)?);
         }
+        Ok(Query::all(queries))
+    }
+
+    fn filterspecs(&self) -> Result<Option<FilterSpecs>, DevInsightError> {
+        if self.filterspecs.is_empty() {
+            return Ok(None);
+        }
+        FilterSpecs::parse(&self.filterspecs).map(Some)
+    }
+
+    // (before, after) context entries; -A and -B take precedence over -C, as in grep
+    fn context_lines(&self) -> (usize, usize) {
+        (
+            self.before_context.or(self.context).unwrap_or(0),
+            self.after_context.or(self.context).unwrap_or(0),
+        )
+    }
 
-        true
-    }
-
-    fn format_log(&self, log: &str) -> String {
-        // Remove debug prints
-        let formatted = if log.contains("E/") || log.contains(" E ") || log.contains("Error:") {
-            format!("{}  {}", "🔴".red().bold(), log.bright_red().bold())
-        } else if log.contains("W/") || log.contains(" W ") || log.contains("Warning:") {
-            format!("{}  {}", "⚠️".yellow().bold(), log.bright_yellow().bold())
-        } else if log.contains("I/") || log.contains(" I ") {
-            format!("{}  {}", "ℹ️".green(), log.bright_green())
-        } else if log.contains("D/") || log.contains(" D ") {
-            format!("{}  {}", "🔧".blue(), log.bright_blue())
-        } else if log.contains("V/") || log.contains(" V ") {
-            format!("{}  {}", "📝".white(), log.bright_white())
+    fn adb_backend(&self) -> AdbBackend {
+        if self.adb_backend != "native" {
+            return AdbBackend::Binary;
+        }
+        AdbBackend::Native(match &self.adb_server {
+            Some(address) => AdbClient::new(address.clone()),
+            None => AdbClient::local(),
+        })
+    }
+}
+
+struct LogProcessor {
+    query: Option<Query>,
+    filterspecs: Option<FilterSpecs>,
+    show_device: bool,  // Prefix lines with the device serial when capturing several
+}
+
+impl LogProcessor {
+    fn new(query: Option<Query>, filterspecs: Option<FilterSpecs>, show_device: bool) -> Self {
+        Self { query, filterspecs, show_device }
+    }
+
+    fn should_process_log(&self, entry: &LogEntry) -> bool {
+        self.query.as_ref().is_none_or(|query| query.matches(entry))
+            && self.filterspecs.as_ref().is_none_or(|specs| specs.matches(entry))
+    }
+
+    fn plain_line(&self, entry: &LogEntry) -> String {
+        // Entries without a logcat header are printed exactly as received
+        let log = if entry.priority == '?' {
+            entry.message.clone()
         } else {
-            format!("{}  {}", "❓".normal(), log)
+            entry.to_logcat_line()
+        };
+        match (self.show_device, &entry.device) {
+            (true, Some(device)) => format!("[{}] {}", device, log),
+            _ => log,
+        }
+    }
+
+    // An entry shown only as context around a match, dimmed and indented past the icon
+    fn format_context(&self, entry: &LogEntry) -> String {
+        format!("    {}", self.plain_line(entry).dimmed())
+    }
+
+    fn format_log(&self, entry: &LogEntry) -> String {
+        let log = self.plain_line(entry);
+
+        let formatted = match entry.level {
+            LogLevel::Assert => format!("{}  {}", "🚨".bright_magenta().bold(), log.bright_magenta().bold()),
+            LogLevel::Fatal => format!("{}  {}", "💀".magenta().bold(), log.magenta().bold()),
+            LogLevel::Error => format!("{}  {}", "🔴".red().bold(), log.bright_red().bold()),
+            LogLevel::Warning => format!("{}  {}", "⚠️".yellow().bold(), log.bright_yellow().bold()),
+            LogLevel::Info => format!("{}  {}", "ℹ️".green(), log.bright_green()),
+            LogLevel::Debug => format!("{}  {}", "🔧".blue(), log.bright_blue()),
+            LogLevel::Verbose => format!("{}  {}", "📝".white(), log.bright_white()),
+            LogLevel::Silent | LogLevel::Unknown => format!("{}  {}", "❓".normal(), log),
         };
 
         // Keep color override
@@ -131,13 +369,189 @@ fn main() -> Result<(), DevInsightError> {
     Ok(())
 }
 
+// Deobfuscation, symbolication and event decoding, applied to every entry before it is
+// stored or shown
+struct Enrichment {
+    retracer: Option<Retracer>,
+    symbolizer: Option<Symbolizer>,
+    event_tags: Option<EventTags>,
+}
+
+impl Enrichment {
+    fn from_cli(cli: &Cli, devices: &[Device]) -> Result<Self, DevInsightError> {
+        let event_tags = match &cli.event_tags {
+            Some(path) => Some(EventTags::load(path)?),
+            None if cli.uses_adb() && cli.reads_events() => EventTags::pull_from_device(&cli.adb_backend(), devices.first().map(|d| d.serial.as_str())),
+            None => None,
+        };
+        Ok(Self {
+            retracer: cli.mapping.as_deref().map(Retracer::load).transpose()?,
+            symbolizer: cli.symbols.as_deref().map(Symbolizer::new).transpose()?,
+            event_tags,
+        })
+    }
+
+    fn apply(&mut self, entry: &mut LogEntry) {
+        if let Some(retracer) = &self.retracer {
+            retracer.retrace_entry(entry);
+        }
+        if let Some(symbolizer) = &mut self.symbolizer {
+            symbolizer.symbolize_entry(entry);
+        }
+        if let Some(event_tags) = &self.event_tags {
+            event_tags.decode_entry(entry);
+        }
+    }
+}
+
+// The devices to capture from: every online device with --all-devices, the -s serials,
+// or otherwise the single attached device. An empty list leaves the choice to adb.
+fn target_devices(cli: &Cli) -> Result<Vec<Device>, DevInsightError> {
+    select_devices(cli, cli.adb_backend().list_devices()?)
+}
+
+fn select_devices(cli: &Cli, attached: Vec<Device>) -> Result<Vec<Device>, DevInsightError> {
+    if cli.all_devices {
+        let online: Vec<Device> = attached.into_iter().filter(Device::is_online).collect();
+        if online.is_empty() {
+            return Err(DevInsightError::DeviceError("no online devices attached".to_string()));
+        }
+        return Ok(online);
+    }
+
+    if !cli.serial.is_empty() {
+        let mut attached = attached;
+        return cli.serial
+            .iter()
+            .map(|serial| {
+                let position = attached.iter().position(|d| &d.serial == serial)
+                    .ok_or_else(|| DevInsightError::DeviceError(format!("{} is not attached", serial)))?;
+                Ok(attached.remove(position))
+            })
+            .collect();
+    }
+
+    let online: Vec<Device> = attached.into_iter().filter(Device::is_online).collect();
+    if online.len() > 1 {
+        let serials: Vec<&str> = online.iter().map(|d| d.serial.as_str()).collect();
+        return Err(DevInsightError::DeviceError(format!(
+            "more than one device attached ({}); pick with -s or use --all-devices",
+            serials.join(", ")
+        )));
+    }
+    Ok(online)
+}
+
+// adb serials to run commands against; None targets adb's default device
+fn device_serials(devices: &[Device]) -> Vec<Option<&str>> {
+    if devices.is_empty() {
+        vec![None]
+    } else {
+        devices.iter().map(|d| Some(d.serial.as_str())).collect()
+    }
+}
+
+// Pick the source named on the command line, falling back to `adb logcat <adb_args>` on
+// each target device
+fn log_source(cli: &Cli, devices: &[Device], adb_args: Vec<String>) -> Result<Arc<dyn LogSource>, DevInsightError> {
+    Ok(if let Some(path) = &cli.load {
+        Arc::new(SessionSource::new(path.clone()))
+    } else if let Some(path) = &cli.file {
+        Arc::new(FileSource::new(path.clone()))
+    } else if let Some(path) = &cli.bugreport {
+        Arc::new(BugreportSource::open(path)?)
+    } else if cli.stdin {
+        Arc::new(StdinSource)
+    } else if let Some(command) = &cli.exec {
+        Arc::new(CommandSource::new(command.clone()))
+    } else {
+        let backend = cli.adb_backend();
+        let mut sources: Vec<Arc<dyn LogSource>> = device_serials(devices)
+            .into_iter()
+            .map(|serial| Arc::new(AdbSource::new(backend.clone(), serial.map(str::to_string), adb_args.clone())) as Arc<dyn LogSource>)
+            .collect();
+        if sources.len() == 1 {
+            sources.remove(0)
+        } else {
+            Arc::new(MultiSource::new(sources))
+        }
+    })
+}
+
+// With --push-filterspecs and a single target device, logcat filters on the device too.
+// Returns whether the specs were pushed.
+fn push_filterspecs(cli: &Cli, devices: &[Device], filterspecs: Option<&FilterSpecs>, adb_args: &mut Vec<String>) -> bool {
+    match filterspecs {
+        Some(specs) if cli.push_filterspecs && cli.uses_adb() && devices.len() <= 1 => {
+            adb_args.extend(specs.logcat_args());
+            true
+        }
+        _ => false,
+    }
+}
+
+// The --package filter, seeded with the app's current pids on each target device
+fn package_filter(cli: &Cli, devices: &[Device]) -> Option<PackageFilter> {
+    let mut filter = PackageFilter::new(cli.package.clone()?, cli.package_crashes);
+    if cli.uses_adb() {
+        for serial in device_serials(devices) {
+            filter.resolve_pids(&cli.adb_backend(), serial);
+        }
+    }
+    Some(filter)
+}
+
+// Empty the selected buffer on every target device
+fn clear_logs(cli: &Cli, devices: &[Device]) -> Result<(), DevInsightError> {
+    for serial in device_serials(devices) {
+        cli.adb_backend().shell(serial, &["logcat", "-b", &cli.buffer, "-c"])?;
+    }
+    Ok(())
+}
+
 fn run_interactive_mode(cli: &Cli) -> Result<(), DevInsightError> {
+    let mut adb_args = cli.logcat_args()?;
+    let query = cli.tag_query()?;
+    let level_filter = cli.level_filter()?;
+    let filterspecs = cli.filterspecs()?;
+    let (before, after) = cli.context_lines();
+    let devices = if cli.uses_adb() { target_devices(cli)? } else { Vec::new() };
+    push_filterspecs(cli, &devices, filterspecs.as_ref(), &mut adb_args);
+    let mut enrichment = Enrichment::from_cli(cli, &devices)?;
+    if cli.clear && cli.uses_adb() {
+        clear_logs(cli, &devices)?;
+    }
+
     // Create channels for logs and storage updates
-    let (log_tx, log_rx) = std::sync::mpsc::channel();
+    let policy = OverflowPolicy::from_name(&cli.overflow).unwrap_or(OverflowPolicy::Block);
+    let (mut log_tx, log_rx) = ingest::channel(cli.queue_size, policy);
     let (storage_tx, storage_rx) = std::sync::mpsc::channel();
-    
+
+    let source = log_source(cli, &devices, adb_args)?;
+    let mut package_filter = package_filter(cli, &devices);
+
+    // A replay reads the whole session up front so the TUI can seek through it
+    if cli.replay {
+        let mut entries = Vec::new();
+        source.run(&mut |mut entry| {
+            enrichment.apply(&mut entry);
+            if package_filter.as_mut().is_none_or(|filter| filter.matches(&entry)) {
+                entries.push(entry);
+            }
+        })?;
+        drop(log_tx);
+        let mut tui = Tui::new(source, log_rx, storage_rx).map_err(DevInsightError::IoError)?;
+        tui.set_filters(level_filter, query.clone(), filterspecs.clone());
+        tui.set_context(before, after);
+        tui.start_replay(Replay::new(entries, cli.speed));
+        tui.run().map_err(DevInsightError::IoError)?;
+        return Ok(());
+    }
+
     // Create TUI with receivers
-    let mut tui = Tui::new(log_rx, storage_rx).map_err(|e| DevInsightError::IoError(e))?;
+    let mut tui = Tui::new(source.clone(), log_rx, storage_rx).map_err(DevInsightError::IoError)?;
+    tui.set_filters(level_filter, query.clone(), filterspecs.clone());
+    tui.set_context(before, after);
     
     // Initialize storage if needed
     let storage = if cli.save {
@@ -150,103 +564,39 @@ fn run_interactive_mode(cli: &Cli) -> Result<(), DevInsightError> {
         None
     };
 
-    // Set up ADB command with optimized buffer settings
-    let process = Command::new("adb")
-        .args(["logcat", 
-              "-v", "threadtime",     // Use threadtime format
-              "-T", "50",            // Get last 50 logs
-              "-b", "all"])          // All buffers
-        .stdout(Stdio::piped())
-        .spawn()
-        .map_err(|_| DevInsightError::AdbNotFound)?;
-
-    let stdout = process.stdout
-        .ok_or(DevInsightError::LogcatCaptureFailed("Failed to capture stdout".to_string()))?;
-    let reader = BufReader::new(stdout);
-
     // Process logs in a separate thread
-    let tx_clone = log_tx.clone();
     let mut storage = storage;  // Move storage into the thread
     std::thread::spawn(move || {
-        for line in reader.lines() {
-            match line {
-                Ok(log) => {
-                    let entry = parse_log_entry(&log);
-                    
-                    // Store log if storage is enabled
-                    if let Some(storage) = &mut storage {
-                        let stored_log = StoredLog {
-                            timestamp: Local::now(),
-                            level: entry.level.as_str().to_string(),
-                            tag: entry.tag.clone(),
-                            message: entry.message.clone(),
-                            device_id: None,
-                        };
-                        storage.store_log(stored_log).ok();
-                    }
-                    
-                    tx_clone.send(entry).ok();
-                }
-                Err(e) => {
-                    eprintln!("Error reading log: {}", e);  // Use eprintln for errors
-                }
+        let result = source.run(&mut |mut entry| {
+            enrichment.apply(&mut entry);
+            if !package_filter.as_mut().is_none_or(|filter| filter.matches(&entry)) {
+                return;
             }
+
+            // Store log if storage is enabled
+            if let Some(storage) = &mut storage {
+                storage.store_log(StoredLog::from_entry(&entry)).ok();
+            }
+
+            log_tx.send(entry);
+        });
+
+        if let Err(e) = result {
+            eprintln!("Error reading log: {}", e);  // Use eprintln for errors
         }
     });
 
     // Run the TUI
-    tui.run().map_err(|e| DevInsightError::IoError(e))?;
+    tui.run().map_err(DevInsightError::IoError)?;
     
     Ok(())
 }
 
-fn parse_log_entry(log: &str) -> LogEntry {
-    // Example threadtime format: "03-21 10:23:45.678  1234  5678 D Tag: Message"
-    let parts: Vec<&str> = log.splitn(2, ':').collect();
-    let message = parts.get(1)
-        .map(|s| s.trim())
-        .unwrap_or(log)
-        .to_string();
-    
-    let header_parts: Vec<&str> = parts.get(0)
-        .unwrap_or(&"")
-        .split_whitespace()
-        .collect();
-    
-    let timestamp = if header_parts.len() >= 2 {
-        format!("{} {}", header_parts[0], header_parts[1])
-    } else {
-        chrono::Local::now().format("%m-%d %H:%M:%S").to_string()
-    };
-
-    let tag = header_parts
-        .iter()
-        .rev()
-        .take(2)
-        .last()
-        .unwrap_or(&"UNKNOWN")
-        .to_string();
-
-    let level = if log.contains(" E ") || log.contains("Error") {
-        LogLevel::Error
-    } else if log.contains(" W ") || log.contains("Warning") {
-        LogLevel::Warning
-    } else if log.contains(" I ") || log.contains("Info") {
-        LogLevel::Info
-    } else if log.contains(" D ") || log.contains("Debug") {
-        LogLevel::Debug
-    } else if log.contains(" V ") || log.contains("Verbose") {
-        LogLevel::Verbose
-    } else {
-        LogLevel::Unknown
-    };
-
-    LogEntry {
-        level,
-        timestamp,
-        tag,
-        message,
-    }
+fn print_incident(record: &CrashRecord) {
+    println!("{}", "=".repeat(50).red());
+    println!("{}", record.summary().red().bold());
+    println!("{}", record.report());
+    println!("{}", "=".repeat(50).red());
 }
 
 // Rename existing main logic
@@ -257,66 +607,76 @@ fn run_standard_mode(cli: Cli) -> Result<(), DevInsightError> {
     println!("{}", "DevInsight: Android Log Analyzer".cyan().bold());
     println!("{}", "=".repeat(50).cyan());
 
-    let processor = LogProcessor::new(cli.filter.clone(), cli.tag.clone());
+    let mut adb_args = cli.logcat_args()?;
+    let query = cli.combined_query()?;
+    let filterspecs = cli.filterspecs()?;
+    let devices = if cli.uses_adb() { target_devices(&cli)? } else { Vec::new() };
+    let pushed = push_filterspecs(&cli, &devices, filterspecs.as_ref(), &mut adb_args);
+    let specs_text = filterspecs.as_ref().map(FilterSpecs::as_str);
+    let processor = LogProcessor::new(query, filterspecs, devices.len() > 1);
+    let (before, after) = cli.context_lines();
+    let mut enrichment = Enrichment::from_cli(&cli, &devices)?;
+    let mut crash_monitor = CrashMonitor::new();
+    let mut package_filter = package_filter(&cli, &devices);
 
     println!("{}", "Starting DevInsight: Real-time Android Log Analyzer...".cyan().bold());
 
     // Clear logs if requested
-    if cli.clear {
-        // Clear logs using separate command
-        Command::new("adb")
-            .args(["logcat", "-c"])
-            .output()
-            .map_err(|_| DevInsightError::AdbNotFound)?;
+    if cli.clear && cli.uses_adb() {
+        clear_logs(&cli, &devices)?;
         println!("{}", "Logs cleared.".green().bold());
     }
 
-    // Build the adb command for monitoring
-    let mut adb_command = Command::new("adb");
-    adb_command.arg("logcat");
-
-    // Add buffer selection - capture all buffers by default
-    adb_command.args(&["-b", "all"]);
-
-    // Add format selection
-    adb_command.arg("-v").arg(&cli.format);
+    let source = log_source(&cli, &devices, adb_args)?;
 
-    // Print the command we're running (for debugging)
-    println!("{}", "Running command:".cyan().bold());
-    println!("{:?}", adb_command);
-
-    // First check if adb is available
-    if Command::new("adb").arg("devices").output().is_err() {
-        return Err(DevInsightError::AdbNotFound);
+    // Print the source we're reading (for debugging)
+    println!("{}", "Reading from:".cyan().bold());
+    println!("{}", source.describe());
+    for device in &devices {
+        println!("Device: {} ({})", device.serial.blue(), device.model.as_deref().unwrap_or("unknown model"));
+    }
+    if let Some(header) = source.header() {
+        println!("Device: {}", header.blue());
     }
-
-    let process = adb_command
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())  // Capture stderr too
-        .spawn()
-        .map_err(|_| DevInsightError::AdbNotFound)?;
-
-    let stdout = process.stdout
-        .ok_or(DevInsightError::LogcatCaptureFailed("Failed to capture stdout".to_string()))?;
-    let reader = BufReader::new(stdout);
 
     // Print command info
     println!("{}", "Log Settings:".yellow().bold());
-    println!("Buffer: All buffers");  // Changed from cli.buffer since we're using all
-    println!("Format: {}", cli.format.blue());
+    println!("Buffer: {}", cli.buffer.blue());
+    if cli.uses_adb() {
+        println!("Format: {}", cli.log_format().blue());
+    }
+    if let Some(since) = &cli.since {
+        println!("Since: {}", since.blue());
+    }
     if let Some(f) = &cli.filter {
         println!("Filter Level: {}", f.blue());
     }
     if let Some(t) = &cli.tag {
         println!("Tag Filter: {}", t.blue());
     }
+    if let Some(q) = &cli.query {
+        println!("Query: {}", q.blue());
+    }
+    if let Some(specs) = &specs_text {
+        let location = if pushed { " (also on device)" } else { "" };
+        println!("Filterspecs: {}{}", specs.blue(), location);
+    }
+    if let Some(package) = &cli.package {
+        println!("Package: {}", package.blue());
+    }
+    if before > 0 || after > 0 {
+        println!("Context: {} before, {} after", before.to_string().blue(), after.to_string().blue());
+    }
     println!("{}", "=".repeat(50).yellow());
 
     // Add a startup message to verify logging is working
-    Command::new("adb")
-        .args(["shell", "log", "-p", "i", "-t", "DevInsight", "Log monitoring started"])
-        .output()
-        .ok();
+    if cli.uses_adb() {
+        for serial in device_serials(&devices) {
+            cli.adb_backend()
+                .shell(serial, &["log", "-p", "i", "-t", "DevInsight", "Log monitoring started"])
+                .ok();
+        }
+    }
 
     // Initialize storage if needed
     let mut storage = if cli.save {
@@ -329,31 +689,154 @@ fn run_standard_mode(cli: Cli) -> Result<(), DevInsightError> {
         None
     };
 
-    for line in reader.lines() {
-        match line {
-            Ok(log) => {
-                if processor.should_process_log(&log) {
-                    // Store log if storage is enabled
-                    if let Some(storage) = &mut storage {
-                        let entry = parse_log_entry(&log);
-                        let stored_log = StoredLog {
-                            timestamp: Local::now(),
-                            level: entry.level.as_str().to_string(),
-                            tag: entry.tag.clone(),
-                            message: entry.message.clone(),
-                            device_id: None,
-                        };
-                        storage.store_log(stored_log).ok();
-                    }
-                    println!("{}", processor.format_log(&log));
+    let mut context = (before > 0 || after > 0).then(|| ContextWindow::new(before, after));
+    let mut pacer = cli.replay.then(|| Pacer::new(cli.speed));
+    // Read on another thread so finished incidents still get reported while the stream is quiet
+    let (log_tx, log_rx) = std::sync::mpsc::sync_channel(STANDARD_QUEUE);
+    let reader = std::thread::spawn(move || source.run(&mut |entry| {
+        log_tx.send(entry).ok();
+    }));
+
+    loop {
+        let mut entry = match log_rx.recv_timeout(STALE_POLL) {
+            Ok(entry) => entry,
+            Err(RecvTimeoutError::Timeout) => {
+                for record in crash_monitor.flush_stale() {
+                    print_incident(&record);
                 }
+                continue;
             }
-            Err(e) => {
-                println!("{}", format!("Error reading log: {}", e).red().bold());
-                break;
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if let Some(pacer) = &mut pacer {
+            pacer.wait_for(&entry);
+        }
+        enrichment.apply(&mut entry);
+
+        // The package filter has to see every entry to keep up with the app's pids
+        let in_package = package_filter.as_mut().is_none_or(|filter| filter.matches(&entry));
+        let matched = in_package && processor.should_process_log(&entry);
+        if matched {
+            // Store log if storage is enabled
+            if let Some(storage) = &mut storage {
+                storage.store_log(StoredLog::from_entry(&entry)).ok();
+            }
+        }
+        match &mut context {
+            Some(context) => {
+                for row in context.push(entry.clone(), matched) {
+                    match row {
+                        ContextRow::Separator => println!("{}", "--".dimmed()),
+                        ContextRow::Context(entry) => println!("{}", processor.format_context(&entry)),
+                        ContextRow::Match(entry) => println!("{}", processor.format_log(&entry)),
+                    }
+                }
             }
+            None if matched => println!("{}", processor.format_log(&entry)),
+            None => {}
         }
+
+        // Incidents are reported regardless of the level/tag filters
+        for record in crash_monitor.feed(&entry) {
+            print_incident(&record);
+        }
+    }
+
+    let result = reader.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+    if let Err(e) = result {
+        println!("{}", format!("Error reading log: {}", e).red().bold());
+    }
+
+    for record in crash_monitor.finish() {
+        print_incident(&record);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+    use device::parse_devices;
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("devinsight").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn logcat_args_with_since() {
+        let args = cli(&["--since", "2024-03-21 10:23:45"]).logcat_args().unwrap();
+        assert_eq!(args, ["-b", "all", "-v", "brief", "-T", "2024-03-21 10:23:45.000"]);
+
+        let args = cli(&["-i", "-b", "main", "--since", "2024-03-21 10:23:45.5"]).logcat_args().unwrap();
+        assert_eq!(args, ["-b", "main", "-v", "threadtime", "-T", "2024-03-21 10:23:45.500"]);
+    }
+
+    #[test]
+    fn logcat_args_reject_a_bad_since() {
+        for since in ["yesterday", "03-21 10:23:45", "2024-13-01 00:00:00"] {
+            match cli(&["--since", since]).logcat_args() {
+                Err(DevInsightError::TimestampError(message)) => assert!(message.contains(since), "{}", message),
+                other => panic!("{}: expected a timestamp error, got {:?}", since, other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn logcat_args_defaults() {
+        assert_eq!(cli(&[]).logcat_args().unwrap(), ["-b", "all", "-v", "brief"]);
+        // The TUI starts with the last 50 entries
+        assert_eq!(cli(&["-i"]).logcat_args().unwrap(), ["-b", "all", "-v", "threadtime", "-T", "50"]);
+        assert_eq!(cli(&["-i", "--format", "long"]).logcat_args().unwrap(), ["-b", "all", "-v", "long", "-T", "50"]);
+    }
+
+    #[test]
+    fn level_filter_by_name_or_letter() {
+        assert!(cli(&[]).level_filter().unwrap().is_none());
+        assert!(matches!(cli(&["--filter", "error"]).level_filter(), Ok(Some(LogLevel::Error))));
+        assert!(matches!(cli(&["-f", "W"]).level_filter(), Ok(Some(LogLevel::Warning))));
+        assert!(matches!(cli(&["-f", "loud"]).level_filter(), Err(DevInsightError::QueryError(_))));
+    }
+
+    #[test]
+    fn tag_is_a_tag_query() {
+        let entry = |tag: &str, message: &str| LogEntry::new(Local::now(), 'E', tag, message);
+        let query = cli(&["-i", "--tag", "Error"]).tag_query().unwrap().unwrap();
+        assert!(query.matches(&entry("ErrorReporter", "sent")));
+        assert!(!query.matches(&entry("MyApp", "Error while loading")));
+
+        let query = cli(&["--tag", "MyApp", "--query", "msg:boom", "-f", "E"]).combined_query().unwrap().unwrap();
+        assert!(query.matches(&entry("MyApp", "boom")));
+        assert!(!query.matches(&entry("MyApp", "fine")));
+        assert!(!query.matches(&LogEntry::new(Local::now(), 'W', "MyApp", "boom")));
+    }
+
+    const DEVICES: &str = "\
+List of devices attached
+emulator-5554          device product:sdk_gphone64_arm64 model:sdk_gphone64_arm64 device:emu64a transport_id:1
+28011FDH2000B9         device usb:1-1 product:panther model:Pixel_7 device:panther transport_id:3
+R58M12345AB            unauthorized usb:1-2 transport_id:4
+";
+
+    fn selected(args: &[&str], devices: &str) -> Result<Vec<String>, DevInsightError> {
+        select_devices(&cli(args), parse_devices(devices))
+            .map(|devices| devices.into_iter().map(|device| device.serial).collect())
+    }
+
+    #[test]
+    fn device_selection() {
+        assert_eq!(selected(&["--all-devices"], DEVICES).unwrap(), ["emulator-5554", "28011FDH2000B9"]);
+        assert_eq!(selected(&["-s", "28011FDH2000B9"], DEVICES).unwrap(), ["28011FDH2000B9"]);
+        // -s takes an attached device as it is; logcat will report why it can't be read
+        assert_eq!(selected(&["-s", "R58M12345AB"], DEVICES).unwrap(), ["R58M12345AB"]);
+        assert!(matches!(selected(&["-s", "missing"], DEVICES), Err(DevInsightError::DeviceError(_))));
+        assert!(matches!(selected(&[], DEVICES), Err(DevInsightError::DeviceError(message)) if message.contains("emulator-5554, 28011FDH2000B9")));
+
+        let one = "List of devices attached\nemulator-5554 device\nR58M12345AB offline\n";
+        assert_eq!(selected(&[], one).unwrap(), ["emulator-5554"]);
+        // Nothing attached leaves the choice to adb
+        assert!(selected(&[], "List of devices attached\n").unwrap().is_empty());
+        assert!(matches!(selected(&["--all-devices"], "List of devices attached\n"), Err(DevInsightError::DeviceError(_))));
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashSet;
+use crate::device::AdbBackend;
+use crate::events::EventValue;
+use crate::tui::LogEntry;
+
+// system_server tags that report on app processes: starts, deaths, ANRs, force-finishes
+const SYSTEM_TAGS: &[&str] = &["ActivityManager", "ActivityTaskManager"];
+
+// debuggerd's tombstone output, written by crash_dump rather than the crashing process
+const TOMBSTONE_TAG: &str = "DEBUG";
+
+// Keeps only one app's entries, following it across restarts the way Android Studio's
+// package filter does: pids are resolved on the device at startup, then updated from
+// ActivityManager's "Start proc" and "has died" lines (or their events-buffer twins).
+pub struct PackageFilter {
+    package: String,
+    include_system: bool,
+    pids: HashSet<(Option<String>, u32)>,  // (device, pid) of the app's processes
+    reporter_pids: HashSet<(Option<String>, u32)>,  // crash_dump processes dumping one of them
+}
+
+impl PackageFilter {
+    pub fn new(package: String, include_system: bool) -> Self {
+        Self {
+            package,
+            include_system,
+            pids: HashSet::new(),
+            reporter_pids: HashSet::new(),
+        }
+    }
+
+    // Find the app's running processes, including ":service" subprocesses. Falls back to
+    // pidof for devices whose ps doesn't understand -A/-o.
+    pub fn resolve_pids(&mut self, backend: &AdbBackend, serial: Option<&str>) {
+        let mut found = Vec::new();
+        if let Ok(output) = backend.shell(serial, &["ps", "-A", "-o", "PID,NAME"]) {
+            for line in output.lines() {
+                let mut columns = line.split_whitespace();
+                let pid = columns.clone().find_map(|column| column.parse::<u32>().ok());
+                if let (Some(pid), Some(name)) = (pid, columns.next_back()) {
+                    if self.is_app_process(name) {
+                        found.push(pid);
+                    }
+                }
+            }
+        }
+        if found.is_empty() {
+            if let Ok(output) = backend.shell(serial, &["pidof", &self.package]) {
+                found.extend(output.split_whitespace().filter_map(|pid| pid.parse::<u32>().ok()));
+            }
+        }
+        let device = serial.map(str::to_string);
+        self.pids.extend(found.into_iter().map(|pid| (device.clone(), pid)));
+    }
+
+    // Update the tracked pids from the entry and decide whether to show it. Entries
+    // without a pid (unparsed lines, DevInsight's own markers) can't be attributed and
+    // are kept.
+    pub fn matches(&mut self, entry: &LogEntry) -> bool {
+        let Some(pid) = entry.pid else {
+            return true;
+        };
+        let lifecycle = self.track(entry);
+
+        if self.pids.contains(&(entry.device.clone(), pid)) {
+            return true;
+        }
+        if !self.include_system {
+            return false;
+        }
+        // "pid: 4242, tid: 4242, name: main  >>> com.example.app <<<"
+        let dumped = entry.message
+            .split_once(">>> ")
+            .and_then(|(_, rest)| rest.split_once(" <<<"))
+            .is_some_and(|(name, _)| self.is_app_process(name));
+        if entry.tag == TOMBSTONE_TAG && dumped {
+            self.reporter_pids.insert((entry.device.clone(), pid));
+        }
+        lifecycle
+            || self.reporter_pids.contains(&(entry.device.clone(), pid))
+            || (SYSTEM_TAGS.contains(&entry.tag.as_str()) && self.mentions_app(&entry.message))
+    }
+
+    // Returns true if the entry started or ended one of the app's processes
+    fn track(&mut self, entry: &LogEntry) -> bool {
+        let (started, died) = match entry.tag.as_str() {
+            "ActivityManager" => (parse_start_proc(&entry.message), parse_has_died(&entry.message)),
+            "am_proc_start" => (event_process(entry), None),
+            "am_proc_died" => (None, event_process(entry)),
+            _ => return false,
+        };
+        if let Some((pid, _)) = started.filter(|(_, name)| self.is_app_process(name)) {
+            self.pids.insert((entry.device.clone(), pid));
+            return true;
+        }
+        if let Some((pid, _)) = died.filter(|(_, name)| self.is_app_process(name)) {
+            self.pids.remove(&(entry.device.clone(), pid));
+            return true;
+        }
+        false
+    }
+
+    fn is_app_process(&self, name: &str) -> bool {
+        name == self.package || name.strip_prefix(&self.package).is_some_and(|rest| rest.starts_with(':'))
+    }
+
+    // The package as a whole word, so com.example.app doesn't match com.example.app2
+    fn mentions_app(&self, message: &str) -> bool {
+        message.match_indices(&self.package).any(|(start, _)| {
+            let end = start + self.package.len();
+            let before = message[..start].chars().next_back();
+            let after = message[end..].chars().next();
+            !before.is_some_and(is_name_char) && !after.is_some_and(is_name_char)
+        })
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+// "Start proc 12345:com.example.app/u0a123 for activity {com.example.app/.MainActivity}"
+fn parse_start_proc(message: &str) -> Option<(u32, String)> {
+    let rest = message.strip_prefix("Start proc ")?;
+    let (pid, rest) = rest.split_once(':')?;
+    let name = rest.split(['/', ' ']).next()?;
+    Some((pid.trim().parse().ok()?, name.to_string()))
+}
+
+// "Process com.example.app (pid 12345) has died: fg  TOP"
+fn parse_has_died(message: &str) -> Option<(u32, String)> {
+    let rest = message.strip_prefix("Process ")?;
+    let (name, rest) = rest.split_once(" (pid ")?;
+    let (pid, rest) = rest.split_once(')')?;
+    if !rest.trim_start().starts_with("has died") {
+        return None;
+    }
+    Some((pid.trim().parse().ok()?, name.to_string()))
+}
+
+// am_proc_start/am_proc_died, once decoded against event-log-tags
+fn event_process(entry: &LogEntry) -> Option<(u32, String)> {
+    let pid = match entry.field("PID")? {
+        EventValue::Int(pid) => u32::try_from(*pid).ok()?,
+        _ => return None,
+    };
+    Some((pid, entry.field("Process Name")?.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+    use crate::events::EventField;
+
+    const APP: &str = "com.example.app";
+
+    fn entry(pid: u32, tag: &str, message: &str) -> LogEntry {
+        let mut entry = LogEntry::new(Local::now(), 'I', tag, message);
+        entry.pid = Some(pid);
+        entry
+    }
+
+    fn event(tag: &str, pid: i64, name: &str) -> LogEntry {
+        let mut entry = entry(1000, tag, "");
+        entry.fields = vec![
+            EventField { name: "PID".to_string(), value: EventValue::Int(pid) },
+            EventField { name: "Process Name".to_string(), value: EventValue::String(name.to_string()) },
+        ];
+        entry
+    }
+
+    fn following(include_system: bool) -> PackageFilter {
+        let mut filter = PackageFilter::new(APP.to_string(), include_system);
+        filter.pids.insert((None, 4242));
+        filter
+    }
+
+    #[test]
+    fn restart_is_followed() {
+        let mut filter = following(false);
+        assert!(filter.matches(&entry(4242, "App", "before")));
+        assert!(!filter.matches(&entry(1000, "ActivityManager", "Process com.example.app (pid 4242) has died: fg  TOP")));
+        assert!(!filter.matches(&entry(1000, "ActivityManager", "Start proc 5151:com.example.app/u0a123 for activity {com.example.app/.Main}")));
+        assert!(filter.matches(&entry(5151, "App", "after")));
+        assert!(!filter.matches(&entry(4242, "App", "stale")));
+    }
+
+    #[test]
+    fn subprocesses_are_followed() {
+        let mut filter = following(false);
+        filter.matches(&entry(1000, "ActivityManager", "Start proc 6000:com.example.app:sync/u0a123 for service"));
+        filter.matches(&entry(1000, "ActivityManager", "Start proc 6001:com.example.app2/u0a124 for service"));
+        assert!(filter.matches(&entry(6000, "Sync", "syncing")));
+        assert!(!filter.matches(&entry(6001, "Other", "not ours")));
+    }
+
+    #[test]
+    fn dead_pid_is_dropped() {
+        let mut filter = following(false);
+        filter.matches(&entry(1000, "ActivityManager", "Process com.example.app (pid 4242) has died: fg  TOP"));
+        assert!(!filter.matches(&entry(4242, "App", "late line")));
+    }
+
+    #[test]
+    fn events_buffer_lifecycle() {
+        let mut filter = following(false);
+        filter.matches(&event("am_proc_died", 4242, APP));
+        filter.matches(&event("am_proc_start", 5151, APP));
+        assert!(!filter.matches(&entry(4242, "App", "old")));
+        assert!(filter.matches(&entry(5151, "App", "new")));
+    }
+
+    #[test]
+    fn pids_are_per_device() {
+        let mut filter = following(false);
+        let mut other = entry(4242, "App", "same pid elsewhere");
+        other.device = Some("emulator-5556".to_string());
+        assert!(!filter.matches(&other));
+    }
+
+    #[test]
+    fn system_lines_need_include_system() {
+        let line = entry(1000, "ActivityManager", "Force finishing activity com.example.app/.Main");
+        assert!(!following(false).matches(&line));
+        assert!(following(true).matches(&line));
+        assert!(following(true).matches(&entry(1000, "ActivityManager", "Process com.example.app (pid 4242) has died: fg  TOP")));
+        assert!(!following(true).matches(&entry(1000, "ActivityManager", "Force finishing activity com.example.app2/.Main")));
+        assert!(!following(true).matches(&entry(1000, "PackageManager", "Updated com.example.app")));
+    }
+
+    #[test]
+    fn tombstone_reporter_follows_the_dump() {
+        let mut filter = following(true);
+        assert!(!filter.matches(&entry(7000, TOMBSTONE_TAG, "*** *** *** *** *** ***")));
+        assert!(filter.matches(&entry(7000, TOMBSTONE_TAG, "pid: 4242, tid: 4242, name: main  >>> com.example.app <<<")));
+        assert!(filter.matches(&entry(7000, TOMBSTONE_TAG, "    #00 pc 0000000000012345  /system/lib64/libc.so (abort+164)")));
+        assert!(!following(false).matches(&entry(7000, TOMBSTONE_TAG, "pid: 4242, tid: 4242, name: main  >>> com.example.app <<<")));
+    }
+
+    #[test]
+    fn unattributed_entries_are_kept() {
+        let mut filter = following(false);
+        assert!(filter.matches(&LogEntry::new(Local::now(), '?', "UNKNOWN", "no header")));
+    }
+
+    #[test]
+    fn mentions_app_as_a_whole_word() {
+        let filter = following(true);
+        assert!(filter.mentions_app("ANR in com.example.app (com.example.app/.Main)"));
+        assert!(filter.mentions_app("killing com.example.app"));
+        assert!(!filter.mentions_app("killing com.example.app2"));
+        assert!(!filter.mentions_app("killing xcom.example.app"));
+    }
+}
@@ -0,0 +1,439 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, TimeZone};
+use crate::tui::{LogEntry, LogLevel};
+
+// Parse a single logcat line, detecting which `-v` format produced it:
+//   brief       "D/Tag( 1234): Message"
+//   process     "D( 1234) Message  (Tag)"
+//   tag         "D/Tag: Message"
+//   thread      "D( 1234: 5678) Message"
+//   time        "03-21 10:23:45.678 D/Tag( 1234): Message"
+//   threadtime  "03-21 10:23:45.678  1234  5678 D Tag: Message"
+//   long        "[ 03-21 10:23:45.678  1234: 5678 D/Tag ]" (message on the following lines)
+// The year, epoch, monotonic, usec/nsec, zone and uid modifiers are accepted in any of them.
+// Anything else (including `-v raw`) is kept as-is with an unknown level.
+pub fn parse_log_entry(log: &str) -> LogEntry {
+    parse_header(log).unwrap_or_else(|| unparsed_entry(log))
+}
+
+// Like `parse_log_entry`, but returns None for lines that carry no logcat header
+pub fn parse_header(line: &str) -> Option<LogEntry> {
+    parse_header_at(line, Local::now())
+}
+
+// Like `parse_header`, for lines read at `now` rather than just now (a bugreport's
+// dumpstate time), which decides the year of "MM-DD" timestamps
+pub fn parse_header_at(line: &str, now: DateTime<Local>) -> Option<LogEntry> {
+    let line = line.trim_end_matches(['\r', '\n']);
+
+    if let Some(inner) = line.strip_prefix("[ ").and_then(|l| l.trim_end().strip_suffix(']')) {
+        return parse_long(inner, now);
+    }
+
+    match parse_time_prefix(line, now) {
+        Some((timestamp, rest)) => parse_brief(rest.trim_start(), timestamp)
+            .or_else(|| parse_threadtime(rest, timestamp)),
+        None => {
+            let timestamp = now;
+            parse_brief(line, timestamp)
+                .or_else(|| parse_process(line, timestamp))
+                .or_else(|| parse_thread(line, timestamp))
+                .or_else(|| parse_tag(line, timestamp))
+        }
+    }
+}
+
+fn unparsed_entry(line: &str) -> LogEntry {
+    LogEntry {
+        timestamp: Local::now(),
+        uid: None,
+        pid: None,
+        tid: None,
+        priority: '?',
+        level: LogLevel::Unknown,
+        tag: "UNKNOWN".to_string(),
+        message: line.trim_end().to_string(),
+        buffer: None,
+        fields: Vec::new(),
+        device: None,
+    }
+}
+
+#[derive(Default)]
+struct Ids {
+    uid: Option<String>,
+    pid: Option<u32>,
+    tid: Option<u32>,
+}
+
+fn build_entry(timestamp: DateTime<Local>, ids: Ids, priority: char, tag: &str, message: &str) -> LogEntry {
+    LogEntry {
+        timestamp,
+        uid: ids.uid,
+        pid: ids.pid,
+        tid: ids.tid,
+        priority,
+        level: LogLevel::from_priority(priority),
+        tag: tag.to_string(),
+        message: message.to_string(),
+        buffer: None,
+        fields: Vec::new(),
+        device: None,
+    }
+}
+
+// "1234  5678 D Tag: Message", optionally preceded by a "10123:" uid column. Bugreports
+// print the uid as a bare column instead ("1000  1234  5678 D Tag: Message").
+fn parse_threadtime(rest: &str, timestamp: DateTime<Local>) -> Option<LogEntry> {
+    let (mut first, mut rest) = next_token(rest)?;
+    let mut uid = None;
+    if let Some(name) = first.strip_suffix(':') {
+        uid = Some(name.to_string());
+        (first, rest) = next_token(rest)?;
+    } else if has_bare_uid(rest) {
+        uid = Some(first.to_string());
+        (first, rest) = next_token(rest)?;
+    }
+    let pid = first.parse::<u32>().ok()?;
+    let (tid, rest) = next_token(rest)?;
+    let tid = tid.parse::<u32>().ok()?;
+
+    let (priority, rest) = next_token(rest)?;
+    let priority = priority_char(priority)?;
+    let (tag, message) = split_tag(rest.trim_start())?;
+
+    let ids = Ids { uid, pid: Some(pid), tid: Some(tid) };
+    Some(build_entry(timestamp, ids, priority, tag, message))
+}
+
+// Whether the tokens after the first are "pid tid priority", making the first a uid
+fn has_bare_uid(rest: &str) -> bool {
+    let tokens: Vec<&str> = rest.split_whitespace().take(3).collect();
+    matches!(tokens.as_slice(), [pid, tid, priority]
+        if pid.parse::<u32>().is_ok() && tid.parse::<u32>().is_ok() && priority_char(priority).is_some())
+}
+
+// "D/Tag( 1234): Message" (brief, and time after its timestamp)
+fn parse_brief(line: &str, timestamp: DateTime<Local>) -> Option<LogEntry> {
+    let (priority, rest) = split_priority(line, '/')?;
+    let close = rest.find("): ")
+        .or_else(|| rest.trim_end().strip_suffix("):").map(str::len))?;
+    let open = rest[..close].rfind('(')?;
+
+    let ids = parse_ids(&rest[open + 1..close], false)?;
+    let tag = rest[..open].trim_end();
+    let message = rest.get(close + 3..).unwrap_or("");
+    Some(build_entry(timestamp, ids, priority, tag, message))
+}
+
+// "D( 1234) Message  (Tag)"
+fn parse_process(line: &str, timestamp: DateTime<Local>) -> Option<LogEntry> {
+    let (priority, rest) = split_priority(line, '(')?;
+    let close = rest.find(')')?;
+    let ids = parse_ids(&rest[..close], false)?;
+
+    let body = rest[close + 1..].strip_prefix(' ')?.trim_end();
+    let tag_start = body.rfind("  (")?;
+    let tag = body[tag_start + 3..].strip_suffix(')')?;
+    Some(build_entry(timestamp, ids, priority, tag, &body[..tag_start]))
+}
+
+// "D( 1234: 5678) Message"
+fn parse_thread(line: &str, timestamp: DateTime<Local>) -> Option<LogEntry> {
+    let (priority, rest) = split_priority(line, '(')?;
+    let close = rest.find(')')?;
+    let ids = parse_ids(&rest[..close], true)?;
+
+    let body = &rest[close + 1..];
+    let message = body.strip_prefix(' ').unwrap_or(body);
+    Some(build_entry(timestamp, ids, priority, "", message))
+}
+
+// "D/Tag: Message"
+fn parse_tag(line: &str, timestamp: DateTime<Local>) -> Option<LogEntry> {
+    let (priority, rest) = split_priority(line, '/')?;
+    let (tag, message) = split_tag(rest)?;
+    Some(build_entry(timestamp, Ids::default(), priority, tag, message))
+}
+
+// "03-21 10:23:45.678  1234: 5678 D/Tag" - the message follows on the next lines
+fn parse_long(inner: &str, now: DateTime<Local>) -> Option<LogEntry> {
+    let (timestamp, rest) = parse_time_prefix(inner, now)?;
+    let rest = rest.trim();
+    let slash = rest.find('/')?;
+    let (ids, priority) = rest[..slash].trim_end().rsplit_once(char::is_whitespace)?;
+    let priority = priority_char(priority)?;
+    let ids = parse_ids(ids, true)?;
+    Some(build_entry(timestamp, ids, priority, rest[slash + 1..].trim_end(), ""))
+}
+
+// The text between parentheses (or before the priority in long format):
+// "1234", "1234: 5678", or either of those preceded by a "10123:" uid
+fn parse_ids(text: &str, with_tid: bool) -> Option<Ids> {
+    let parts: Vec<&str> = text.split(':').map(str::trim).collect();
+    let expected = if with_tid { 2 } else { 1 };
+    let (uid, numbers) = match parts.len() {
+        n if n == expected => (None, &parts[..]),
+        n if n == expected + 1 => (Some(parts[0].to_string()), &parts[1..]),
+        _ => return None,
+    };
+
+    let pid = numbers[0].parse::<u32>().ok()?;
+    let tid = if with_tid { Some(numbers[1].parse::<u32>().ok()?) } else { None };
+    Some(Ids { uid, pid: Some(pid), tid })
+}
+
+// Leading priority letter followed by `separator`, e.g. "D/" or "D("
+fn split_priority(line: &str, separator: char) -> Option<(char, &str)> {
+    let mut chars = line.chars();
+    let priority = chars.next()?;
+    if !is_priority(priority) || chars.next()? != separator {
+        return None;
+    }
+    Some((priority, &line[2..]))
+}
+
+fn priority_char(token: &str) -> Option<char> {
+    let mut chars = token.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if is_priority(c) => Some(c),
+        _ => None,
+    }
+}
+
+fn is_priority(c: char) -> bool {
+    matches!(c, 'V' | 'D' | 'I' | 'W' | 'E' | 'F' | 'A' | 'S')
+}
+
+// Tags are padded to 8 columns and followed by ": ", so the first ": " ends the tag.
+// An empty message leaves only the trailing ':'.
+fn split_tag(rest: &str) -> Option<(&str, &str)> {
+    if let Some(pos) = rest.find(": ") {
+        Some((rest[..pos].trim_end(), &rest[pos + 2..]))
+    } else {
+        rest.trim_end().strip_suffix(':').map(|tag| (tag.trim_end(), ""))
+    }
+}
+
+// Recognise any of logcat's timestamp layouts at the start of a line:
+//   "03-21 10:23:45.678"         default, usec/nsec add more digits
+//   "2024-03-21 10:23:45.678"    -v year
+//   "1711016625.678"             -v epoch
+//   "  1234.567"                 -v monotonic (seconds since boot)
+// optionally followed by a "+0100" offset with -v zone.
+fn parse_time_prefix(line: &str, now: DateTime<Local>) -> Option<(DateTime<Local>, &str)> {
+    let (first, rest) = next_token(line)?;
+
+    if let Some((seconds, _)) = first.split_once('.') {
+        if first.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            let value: f64 = first.parse().ok()?;
+            let timestamp = if seconds.len() >= 9 {
+                Local.timestamp_millis_opt((value * 1000.0) as i64).single()?
+            } else {
+                // Monotonic time has no wall-clock anchor, so use the arrival time
+                now
+            };
+            return Some((timestamp, rest));
+        }
+    }
+
+    let (time, rest) = next_token(rest)?;
+    if !time.contains(':') {
+        return None;
+    }
+
+    let (zone, rest) = match next_token(rest) {
+        Some((zone, after)) if is_zone(zone) => (Some(zone), after),
+        _ => (None, rest),
+    };
+
+    let timestamp = parse_timestamp(first, time, zone, now)?;
+    Some((timestamp, rest))
+}
+
+fn is_zone(token: &str) -> bool {
+    token.len() == 5
+        && token.starts_with(['+', '-'])
+        && token[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+// "MM-DD HH:MM:SS.mmm" carries no year, so assume the current one unless that
+// would put the entry in the future (logs captured just after New Year) or the
+// date doesn't exist this year (02-29 after a leap year).
+fn parse_timestamp(date: &str, time: &str, zone: Option<&str>, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let parse = |date: &str| -> Option<DateTime<Local>> {
+        match zone {
+            Some(zone) => DateTime::parse_from_str(&format!("{} {} {}", date, time, zone), "%Y-%m-%d %H:%M:%S%.f %z")
+                .ok()
+                .map(|dt| dt.with_timezone(&Local)),
+            None => NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S%.f")
+                .ok()
+                .and_then(|naive| Local.from_local_datetime(&naive).earliest()),
+        }
+    };
+
+    if date.matches('-').count() == 2 {
+        return parse(date);
+    }
+
+    match parse(&format!("{}-{}", now.year(), date)) {
+        Some(timestamp) if timestamp <= now + Duration::days(1) => Some(timestamp),
+        _ => parse(&format!("{}-{}", now.year() - 1, date)),
+    }
+}
+
+fn next_token(input: &str) -> Option<(&str, &str)> {
+    let input = input.trim_start();
+    if input.is_empty() {
+        return None;
+    }
+    let end = input.find(char::is_whitespace).unwrap_or(input.len());
+    Some((&input[..end], &input[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    fn parse(line: &str) -> LogEntry {
+        parse_header(line).unwrap_or_else(|| panic!("no header in {:?}", line))
+    }
+
+    #[test]
+    fn brief() {
+        let entry = parse("D/ActivityManager( 1234): Start proc");
+        assert_eq!((entry.priority, entry.tag.as_str(), entry.pid), ('D', "ActivityManager", Some(1234)));
+        assert_eq!(entry.message, "Start proc");
+    }
+
+    #[test]
+    fn process() {
+        let entry = parse("W( 1234) Slow operation  (Looper)");
+        assert_eq!((entry.priority, entry.tag.as_str(), entry.pid), ('W', "Looper", Some(1234)));
+        assert_eq!(entry.message, "Slow operation");
+    }
+
+    #[test]
+    fn tag() {
+        let entry = parse("I/chatty: uid=1000 expire 3 lines");
+        assert_eq!((entry.priority, entry.tag.as_str(), entry.pid), ('I', "chatty", None));
+        assert_eq!(entry.message, "uid=1000 expire 3 lines");
+    }
+
+    #[test]
+    fn thread() {
+        let entry = parse("E( 1234: 5678) Something failed");
+        assert_eq!((entry.priority, entry.pid, entry.tid), ('E', Some(1234), Some(5678)));
+        assert_eq!(entry.message, "Something failed");
+    }
+
+    #[test]
+    fn time() {
+        let entry = parse("03-21 10:23:45.678 D/Tag( 1234): Message");
+        assert_eq!((entry.tag.as_str(), entry.pid), ("Tag", Some(1234)));
+        assert_eq!(entry.timestamp.format("%m-%d %H:%M:%S%.3f").to_string(), "03-21 10:23:45.678");
+    }
+
+    #[test]
+    fn threadtime() {
+        let entry = parse("03-21 10:23:45.678  1234  5678 D Tag     : Message: with colon");
+        assert_eq!((entry.priority, entry.pid, entry.tid), ('D', Some(1234), Some(5678)));
+        assert_eq!((entry.tag.as_str(), entry.message.as_str()), ("Tag", "Message: with colon"));
+    }
+
+    #[test]
+    fn long_header() {
+        let entry = parse("[ 03-21 10:23:45.678  1234: 5678 W/Tag ]");
+        assert_eq!((entry.priority, entry.tag.as_str()), ('W', "Tag"));
+        assert_eq!((entry.pid, entry.tid), (Some(1234), Some(5678)));
+        assert_eq!(entry.message, "");
+    }
+
+    #[test]
+    fn raw_is_unparsed() {
+        assert!(parse_header("just some text").is_none());
+        let entry = parse_log_entry("just some text");
+        assert_eq!((entry.priority, entry.level), ('?', LogLevel::Unknown));
+        assert_eq!(entry.message, "just some text");
+    }
+
+    #[test]
+    fn year_modifier() {
+        let entry = parse("2023-03-21 10:23:45.678  1234  5678 I Tag: Message");
+        assert_eq!(entry.timestamp.year(), 2023);
+    }
+
+    #[test]
+    fn epoch_modifier() {
+        let entry = parse("1711016625.678  1234  5678 I Tag: Message");
+        assert_eq!(entry.timestamp.timestamp_millis(), 1_711_016_625_678);
+    }
+
+    #[test]
+    fn monotonic_modifier() {
+        let entry = parse("  1234.567  1234  5678 I Tag: Message");
+        assert_eq!((entry.pid, entry.tag.as_str()), (Some(1234), "Tag"));
+    }
+
+    #[test]
+    fn usec_modifier() {
+        let entry = parse("03-21 10:23:45.678901  1234  5678 I Tag: Message");
+        assert_eq!(entry.timestamp.nanosecond(), 678_901_000);
+    }
+
+    #[test]
+    fn zone_modifier() {
+        let entry = parse("2024-03-21 10:23:45.678 +0000  1234  5678 I Tag: Message");
+        assert_eq!(entry.timestamp.timestamp_millis(), 1_711_016_625_678);
+    }
+
+    #[test]
+    fn uid_modifier() {
+        let entry = parse("03-21 10:23:45.678 u0_a123:  1234  5678 I Tag: Message");
+        assert_eq!((entry.uid.as_deref(), entry.pid), (Some("u0_a123"), Some(1234)));
+        let entry = parse("03-21 10:23:45.678 10123:  1234  5678 I Tag: Message");
+        assert_eq!((entry.uid.as_deref(), entry.pid), (Some("10123"), Some(1234)));
+        let entry = parse("D/Tag(10123: 1234): Message");
+        assert_eq!((entry.uid.as_deref(), entry.pid), (Some("10123"), Some(1234)));
+    }
+
+    #[test]
+    fn bare_uid_from_bugreport() {
+        let entry = parse("03-21 10:23:45.678  1000  1234  5678 I Tag: Message");
+        assert_eq!(entry.uid.as_deref(), Some("1000"));
+        assert_eq!((entry.pid, entry.tid), (Some(1234), Some(5678)));
+        assert!(!has_bare_uid("  5678 I Tag: Message"));
+    }
+
+    #[test]
+    fn new_year_rollback() {
+        // A date a few days ahead can only be from last year, unless it's in January
+        // already and so still this year
+        let now = Local::now();
+        let ahead = (10..13)
+            .map(|days| now + Duration::days(days))
+            .find(|date| !(date.month() == 2 && date.day() == 29))
+            .unwrap();
+        let expected = if ahead.year() == now.year() { now.year() - 1 } else { now.year() };
+        let timestamp = parse_timestamp(&ahead.format("%m-%d").to_string(), "10:00:00.000", None, now).unwrap();
+        assert_eq!(timestamp.year(), expected);
+
+        let today = parse_timestamp(&now.format("%m-%d").to_string(), "00:00:00.000", None, now).unwrap();
+        assert_eq!(today.year(), now.year());
+    }
+
+    #[test]
+    fn leap_day_after_a_leap_year() {
+        let now = Local.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+        let timestamp = parse_timestamp("02-29", "10:00:00.000", None, now).unwrap();
+        assert_eq!((timestamp.year(), timestamp.month(), timestamp.day()), (2024, 2, 29));
+
+        let now = Local.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let timestamp = parse_timestamp("02-29", "10:00:00.000", None, now).unwrap();
+        assert_eq!(timestamp.year(), 2024);
+
+        let now = Local.with_ymd_and_hms(2025, 1, 2, 12, 0, 0).unwrap();
+        let timestamp = parse_timestamp("12-31", "23:59:59.000", None, now).unwrap();
+        assert_eq!(timestamp.year(), 2024);
+    }
+}
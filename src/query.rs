@@ -0,0 +1,560 @@
+use std::cmp::Ordering;
+use regex::Regex;
+use crate::events::EventValue;
+use crate::tui::{LogEntry, LogLevel};
+use crate::DevInsightError;
+
+// A parsed filter expression, e.g. `level>=W && tag~/^Net/ && !msg:"heartbeat" && pid==1234`.
+//
+//   expr       := term ("||" term)*
+//   term       := factor ("&&" factor)*
+//   factor     := "!" factor | "(" expr ")" | comparison | text
+//   comparison := field op value
+//   field      := word | "quoted"
+//   op         := == != > >= < <= (compare)  : (contains)  ~ (regex)
+//   value      := word | "quoted" | /regex/ (add i for case-insensitive)
+//
+// Fields are level, tag, msg (or message), pid, tid, uid, buffer and device, in lower
+// case; any other name is looked up among the entry's decoded event fields, and
+// `event.<name>` always is, so an event's own `PID` or `"Process Name"` can be reached.
+// A bare word or string matches the tag or message, ignoring case.
+#[derive(Clone)]
+pub struct Query {
+    text: String,
+    expr: Expr,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Self, DevInsightError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, position: 0, end_column: text.chars().count() + 1 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(error(token.column, format!("unexpected '{}'", token.kind)));
+        }
+        Ok(Self { text: text.to_string(), expr })
+    }
+
+    // Entries matching every one of the queries
+    pub fn all(queries: Vec<Query>) -> Option<Query> {
+        queries.into_iter().reduce(|left, right| Query {
+            text: format!("({}) && ({})", left.text, right.text),
+            expr: Expr::And(Box::new(left.expr), Box::new(right.expr)),
+        })
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.expr.matches(entry)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Value),
+    Text(String),  // Lowercased
+}
+
+#[derive(Clone)]
+enum Field {
+    Level,
+    Tag,
+    Message,
+    Pid,
+    Tid,
+    Uid,
+    Buffer,
+    Device,
+    Event(String),
+}
+
+impl Field {
+    fn from_name(name: &str) -> Self {
+        if let Some(event) = name.strip_prefix("event.") {
+            return Field::Event(event.to_string());
+        }
+        match name {
+            "level" => Field::Level,
+            "tag" => Field::Tag,
+            "msg" | "message" => Field::Message,
+            "pid" => Field::Pid,
+            "tid" => Field::Tid,
+            "uid" => Field::Uid,
+            "buffer" => Field::Buffer,
+            "device" => Field::Device,
+            _ => Field::Event(name.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+    Matches,
+}
+
+impl Op {
+    fn is_ordering(self) -> bool {
+        matches!(self, Op::Gt | Op::Ge | Op::Lt | Op::Le)
+    }
+
+    fn accepts(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Contains | Op::Matches => false,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Value {
+    Level(LogLevel),
+    Number(f64),
+    Text(String),  // Lowercased for ':'
+    Regex(Regex),
+}
+
+impl Expr {
+    fn matches(&self, entry: &LogEntry) -> bool {
+        match self {
+            Expr::And(left, right) => left.matches(entry) && right.matches(entry),
+            Expr::Or(left, right) => left.matches(entry) || right.matches(entry),
+            Expr::Not(inner) => !inner.matches(entry),
+            Expr::Text(text) => {
+                entry.tag.to_lowercase().contains(text) || entry.message.to_lowercase().contains(text)
+            }
+            Expr::Compare(field, op, value) => compare(entry, field, *op, value),
+        }
+    }
+}
+
+fn compare(entry: &LogEntry, field: &Field, op: Op, value: &Value) -> bool {
+    let text = match field {
+        Field::Level => {
+            return match value {
+                Value::Level(level) => op.accepts(entry.level.cmp(level)),
+                _ => false,
+            };
+        }
+        Field::Pid | Field::Tid => {
+            let id = if matches!(field, Field::Pid) { entry.pid } else { entry.tid };
+            return match (id, value) {
+                (Some(id), Value::Number(expected)) => compare_number(f64::from(id), op, *expected),
+                _ => false,
+            };
+        }
+        Field::Tag => entry.tag.clone(),
+        Field::Message => entry.message.clone(),
+        Field::Uid => match &entry.uid {
+            Some(uid) => uid.clone(),
+            None => return false,
+        },
+        Field::Buffer => match &entry.buffer {
+            Some(buffer) => buffer.clone(),
+            None => return false,
+        },
+        Field::Device => match &entry.device {
+            Some(device) => device.clone(),
+            None => return false,
+        },
+        Field::Event(name) => match (entry.field(name), value) {
+            (Some(EventValue::Int(actual)), Value::Number(expected)) => {
+                return compare_number(*actual as f64, op, *expected);
+            }
+            (Some(EventValue::Float(actual)), Value::Number(expected)) => {
+                return compare_number(*actual, op, *expected);
+            }
+            (Some(field), _) => field.to_string(),
+            (None, _) => return false,
+        },
+    };
+
+    match (op, value) {
+        (Op::Eq, Value::Text(expected)) => text == *expected,
+        (Op::Ne, Value::Text(expected)) => text != *expected,
+        (Op::Contains, Value::Text(expected)) => text.to_lowercase().contains(expected.as_str()),
+        (Op::Matches, Value::Regex(regex)) => regex.is_match(&text),
+        (_, Value::Number(expected)) => text.parse().is_ok_and(|actual| compare_number(actual, op, *expected)),
+        _ => false,
+    }
+}
+
+fn compare_number(actual: f64, op: Op, expected: f64) -> bool {
+    actual.partial_cmp(&expected).is_some_and(|ordering| op.accepts(ordering))
+}
+
+#[derive(Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Quoted(String),
+    Regex(String),
+    Op(&'static str),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::Word(word) => write!(f, "{}", word),
+            TokenKind::Quoted(text) => write!(f, "\"{}\"", text),
+            TokenKind::Regex(pattern) => write!(f, "/{}/", pattern),
+            TokenKind::Op(op) => write!(f, "{}", op),
+            TokenKind::And => write!(f, "&&"),
+            TokenKind::Or => write!(f, "||"),
+            TokenKind::Not => write!(f, "!"),
+            TokenKind::Open => write!(f, "("),
+            TokenKind::Close => write!(f, ")"),
+        }
+    }
+}
+
+struct Token {
+    kind: TokenKind,
+    column: usize,  // 1-based, for error messages
+}
+
+const OPERATORS: &[&str] = &["==", "!=", ">=", "<=", ">", "<", ":", "~"];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, DevInsightError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let rest: String = chars[i..].iter().take(2).collect();
+        let kind = if rest == "&&" {
+            i += 2;
+            TokenKind::And
+        } else if rest == "||" {
+            i += 2;
+            TokenKind::Or
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            i += op.len();
+            TokenKind::Op(op)
+        } else if c == '!' {
+            i += 1;
+            TokenKind::Not
+        } else if c == '(' {
+            i += 1;
+            TokenKind::Open
+        } else if c == ')' {
+            i += 1;
+            TokenKind::Close
+        } else if c == '"' || c == '/' {
+            let (body, end) = delimited(&chars, i, c)
+                .ok_or_else(|| error(column, format!("missing closing {}", c)))?;
+            i = end;
+            if c == '"' {
+                TokenKind::Quoted(body)
+            } else if chars.get(i) == Some(&'i') && !chars.get(i + 1).is_some_and(|c| is_word_char(*c)) {
+                i += 1;
+                TokenKind::Regex(format!("(?i){}", body))
+            } else {
+                TokenKind::Regex(body)
+            }
+        } else if is_word_char(c) {
+            let start = i;
+            while i < chars.len() && is_word_char(chars[i]) {
+                i += 1;
+            }
+            TokenKind::Word(chars[start..i].iter().collect())
+        } else {
+            return Err(error(column, format!("unexpected '{}'", c)));
+        };
+        tokens.push(Token { kind, column });
+    }
+    Ok(tokens)
+}
+
+// A '/' only starts a regex at the beginning of a value; inside a word it's part of it,
+// as in a path or "ActivityManager/foo"
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !"()!&|=<>~:\"".contains(c)
+}
+
+// The text between a delimiter at `start` and its closing twin, with \-escapes of the
+// delimiter resolved, and the index just past the close
+fn delimited(chars: &[char], start: usize, delimiter: char) -> Option<(String, usize)> {
+    let mut body = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if chars.get(i + 1) == Some(&delimiter) => {
+                body.push(delimiter);
+                i += 2;
+            }
+            c if c == delimiter => return Some((body, i + 1)),
+            c => {
+                body.push(c);
+                i += 1;
+            }
+        }
+    }
+    None
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    end_column: usize,  // Where "unexpected end of query" points
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|token| &token.kind)
+    }
+
+    fn next(&mut self) -> Result<Token, DevInsightError> {
+        let token = self.tokens.get(self.position)
+            .ok_or_else(|| error(self.end_column, "unexpected end of query".to_string()))?;
+        self.position += 1;
+        Ok(Token { kind: token.kind.clone(), column: token.column })
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, DevInsightError> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&TokenKind::Or) {
+            self.position += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, DevInsightError> {
+        let mut expr = self.parse_factor()?;
+        while self.peek() == Some(&TokenKind::And) {
+            self.position += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_factor()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_factor(&mut self) -> Result<Expr, DevInsightError> {
+        let token = self.next()?;
+        match token.kind {
+            TokenKind::Not => Ok(Expr::Not(Box::new(self.parse_factor()?))),
+            TokenKind::Open => {
+                let expr = self.parse_or()?;
+                let close = self.next()?;
+                if close.kind != TokenKind::Close {
+                    return Err(error(close.column, format!("expected ')' but found '{}'", close.kind)));
+                }
+                Ok(expr)
+            }
+            TokenKind::Word(name) | TokenKind::Quoted(name) => match self.peek() {
+                Some(&TokenKind::Op(op)) => {
+                    let column = self.next()?.column;
+                    let value = self.next()?;
+                    comparison(&name, op, column, value)
+                }
+                _ => Ok(Expr::Text(name.to_lowercase())),
+            },
+            other => Err(error(token.column, format!("unexpected '{}'", other))),
+        }
+    }
+}
+
+// Check the operator and value make sense for the field and compile the value
+fn comparison(name: &str, op: &str, op_column: usize, value: Token) -> Result<Expr, DevInsightError> {
+    let field = Field::from_name(name);
+    let op = match op {
+        "==" => Op::Eq,
+        "!=" => Op::Ne,
+        ">" => Op::Gt,
+        ">=" => Op::Ge,
+        "<" => Op::Lt,
+        "<=" => Op::Le,
+        ":" => Op::Contains,
+        _ => Op::Matches,
+    };
+    let (raw, is_regex) = match value.kind {
+        TokenKind::Word(word) => (word, false),
+        TokenKind::Quoted(text) => (text, false),
+        TokenKind::Regex(pattern) => (pattern, true),
+        other => return Err(error(value.column, format!("expected a value but found '{}'", other))),
+    };
+    let unsupported = || error(op_column, format!("'{}' can't be compared with that operator", name));
+
+    let value = match (&field, op) {
+        (Field::Level, Op::Contains | Op::Matches) => return Err(unsupported()),
+        (Field::Level, _) => Value::Level(
+            LogLevel::from_name(&raw).ok_or_else(|| error(value.column, format!("unknown level '{}'", raw)))?,
+        ),
+        (Field::Pid | Field::Tid, Op::Contains | Op::Matches) => return Err(unsupported()),
+        (Field::Pid | Field::Tid, _) => Value::Number(
+            raw.parse().map_err(|_| error(value.column, format!("'{}' is not a number", raw)))?,
+        ),
+        (_, Op::Matches) => Value::Regex(
+            Regex::new(&raw).map_err(|e| error(value.column, format!("invalid regex: {}", regex_problem(&e))))?,
+        ),
+        (_, _) if is_regex => return Err(error(value.column, "a /regex/ needs the ~ operator".to_string())),
+        (Field::Event(_), _) if op.is_ordering() => Value::Number(
+            raw.parse().map_err(|_| error(value.column, format!("'{}' is not a number", raw)))?,
+        ),
+        (_, _) if op.is_ordering() => return Err(unsupported()),
+        (_, Op::Contains) => Value::Text(raw.to_lowercase()),
+        (Field::Event(_), _) => match raw.parse() {
+            Ok(number) => Value::Number(number),
+            Err(_) => Value::Text(raw),
+        },
+        (_, _) => Value::Text(raw),
+    };
+    Ok(Expr::Compare(field, op, value))
+}
+
+// The regex crate's syntax errors draw the pattern with a caret over several lines; keep
+// just the description so it fits a status line
+pub fn regex_problem(error: &regex::Error) -> String {
+    let text = error.to_string();
+    let last = text.lines().last().unwrap_or_default();
+    last.strip_prefix("error: ").unwrap_or(last).to_string()
+}
+
+fn error(column: usize, message: String) -> DevInsightError {
+    DevInsightError::QueryError(format!("{} (at column {})", message, column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+    use crate::events::EventField;
+
+    fn entry() -> LogEntry {
+        let mut entry = LogEntry::new(Local::now(), 'W', "NetworkMonitor", "Probe failed: heartbeat timeout");
+        entry.pid = Some(1234);
+        entry.tid = Some(1250);
+        entry.buffer = Some("main".to_string());
+        entry.fields = vec![
+            EventField { name: "PID".to_string(), value: EventValue::Int(4242) },
+            EventField { name: "Process Name".to_string(), value: EventValue::String("com.example.app".to_string()) },
+            EventField { name: "load".to_string(), value: EventValue::Float(0.75) },
+        ];
+        entry
+    }
+
+    fn matches(query: &str) -> bool {
+        Query::parse(query).unwrap_or_else(|e| panic!("{}: {}", query, e)).matches(&entry())
+    }
+
+    fn parse_error(query: &str) -> String {
+        match Query::parse(query) {
+            Err(DevInsightError::QueryError(message)) => message,
+            Err(other) => panic!("{}: unexpected error {}", query, other),
+            Ok(_) => panic!("{}: parsed", query),
+        }
+    }
+
+    fn kinds(text: &str) -> Vec<String> {
+        tokenize(text).unwrap().iter().map(|token| token.kind.to_string()).collect()
+    }
+
+    #[test]
+    fn tokenizer_splits_operators_and_values() {
+        assert_eq!(kinds("level>=W&&!(tag~/^Net/i)"), ["level", ">=", "W", "&&", "!", "(", "tag", "~", "/(?i)^Net/", ")"]);
+        assert_eq!(kinds(r#"msg:"say \"hi\"" || pid!=7"#), ["msg", ":", "\"say \"hi\"\"", "||", "pid", "!=", "7"]);
+        assert_eq!(kinds("a/b/c"), ["a/b/c"]);
+        assert_eq!(kinds("tag:ActivityManager/foo && /b/i"), ["tag", ":", "ActivityManager/foo", "&&", "/(?i)b/"]);
+    }
+
+    #[test]
+    fn slashes_inside_words() {
+        let mut entry = entry();
+        entry.tag = "ActivityManager/foo".to_string();
+        entry.message = "opened /data/local/tmp/app.log".to_string();
+        let query = |text: &str| Query::parse(text).unwrap_or_else(|e| panic!("{}: {}", text, e)).matches(&entry);
+        assert!(query("tag:ActivityManager/foo"));
+        assert!(query("msg:local/tmp/app.log && msg~/tmp\\/app/"));
+        assert!(query("data/local"));
+        // A leading '/' still starts a regex, so such a path needs quotes
+        assert!(query("msg:\"/data/local\""));
+        assert!(!query("msg:nope/local"));
+    }
+
+    #[test]
+    fn tokenizer_reports_columns() {
+        let tokens = tokenize("tag == Foo").unwrap();
+        let columns: Vec<usize> = tokens.iter().map(|token| token.column).collect();
+        assert_eq!(columns, [1, 5, 8]);
+        assert_eq!(parse_error("msg:\"open"), "missing closing \" (at column 5)");
+        assert_eq!(parse_error("tag==/x"), "missing closing / (at column 6)");
+    }
+
+    #[test]
+    fn parser_follows_precedence() {
+        assert!(matches("tag==Nope && pid==1 || level==W"));
+        assert!(!matches("tag==Nope && (pid==1 || level==W)"));
+        assert!(matches("!tag==Nope && !!level==W"));
+        assert!(matches("heartbeat"));
+        assert!(matches("\"PROBE FAILED\""));
+    }
+
+    #[test]
+    fn parser_reports_structural_errors() {
+        assert_eq!(parse_error("(level==W"), "unexpected end of query (at column 10)");
+        assert_eq!(parse_error("level==W)"), "unexpected ')' (at column 9)");
+        assert_eq!(parse_error("level== &&"), "expected a value but found '&&' (at column 9)");
+        assert_eq!(parse_error("&& tag==x"), "unexpected '&&' (at column 1)");
+    }
+
+    #[test]
+    fn built_in_fields() {
+        assert!(matches("level>=I && level<E"));
+        assert!(matches("level==warning"));
+        assert!(matches("tag==NetworkMonitor && tag!=Network"));
+        assert!(matches("msg:TIMEOUT && message~/^Probe/"));
+        assert!(matches("pid==1234 && tid>1249 && tid<=1250"));
+        assert!(matches("buffer==main"));
+        assert!(!matches("uid==10123"));
+        assert!(!matches("device:emulator"));
+    }
+
+    #[test]
+    fn event_fields() {
+        assert!(matches("PID==4242"));
+        assert!(matches("event.PID==4242 && event.pid>4000"));
+        assert!(matches("\"Process Name\":example && \"event.Process Name\"==com.example.app"));
+        assert!(matches("load>=0.5 && load<1"));
+        assert!(!matches("missing==1"));
+    }
+
+    #[test]
+    fn operator_checks() {
+        assert_eq!(parse_error("level:W"), "'level' can't be compared with that operator (at column 6)");
+        assert_eq!(parse_error("pid~/12/"), "'pid' can't be compared with that operator (at column 4)");
+        assert_eq!(parse_error("tag>A"), "'tag' can't be compared with that operator (at column 4)");
+        assert_eq!(parse_error("level==X"), "unknown level 'X' (at column 8)");
+        assert_eq!(parse_error("pid==abc"), "'abc' is not a number (at column 6)");
+        assert_eq!(parse_error("load>high"), "'high' is not a number (at column 6)");
+        assert_eq!(parse_error("tag==/Net/"), "a /regex/ needs the ~ operator (at column 6)");
+        assert!(parse_error("tag~/(/").starts_with("invalid regex: "));
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+use std::time::Duration;
+use chrono::{DateTime, Local};
+use crate::tui::{LogEntry, TIMESTAMP_FORMAT};
+use crate::DevInsightError;
+
+// Pause between restarts so a logcat that keeps failing doesn't spin. It doubles for
+// each restart in a row that delivers nothing, up to MAX_RESTART_DELAY.
+pub const RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+// A logcat that keeps exiting with nothing to show while its device stays online isn't
+// going to recover (e.g. the device rejects the arguments), so stop restarting it
+pub const MAX_EMPTY_RESTARTS: u32 = 5;
+
+pub fn restart_delay(empty_restarts: u32) -> Duration {
+    RESTART_DELAY.saturating_mul(1 << empty_restarts.min(16)).min(MAX_RESTART_DELAY)
+}
+
+// Where a restarted logcat should pick up: the newest timestamp delivered so far and the
+// entries already delivered at exactly that time. `logcat -T <time>` starts at and
+// includes that time, so those entries come round again and have to be skipped.
+#[derive(Clone)]
+pub struct ResumePoint {
+    timestamp: DateTime<Local>,
+    seen: HashSet<String>,
+}
+
+impl ResumePoint {
+    // Fold a delivered entry into the resume point
+    pub fn record(point: &mut Option<ResumePoint>, entry: &LogEntry) {
+        // Dividers and unparsed lines carry the host's clock, not the device's
+        if entry.priority == '?' {
+            return;
+        }
+        match point {
+            Some(point) if entry.timestamp < point.timestamp => {}
+            Some(point) if entry.timestamp == point.timestamp => {
+                point.seen.insert(entry.to_logcat_line());
+            }
+            _ => {
+                *point = Some(ResumePoint {
+                    timestamp: entry.timestamp,
+                    seen: HashSet::from([entry.to_logcat_line()]),
+                });
+            }
+        }
+    }
+
+    // The logcat arguments with any existing -T/-t start replaced by this point. The new
+    // -T goes first so trailing filterspecs stay at the end, where logcat expects them.
+    pub fn logcat_args(&self, args: &[String]) -> Vec<String> {
+        let mut resumed = vec!["-T".to_string(), self.timestamp.format(TIMESTAMP_FORMAT).to_string()];
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "-T" || arg == "-t" {
+                args.next();
+            } else {
+                resumed.push(arg.clone());
+            }
+        }
+        resumed
+    }
+
+    // Whether an entry from the restarted logcat was already delivered before the gap.
+    // Returns None once the stream has moved past the resume point.
+    pub fn is_duplicate(&self, entry: &LogEntry) -> Option<bool> {
+        if entry.priority == '?' {
+            return Some(false);
+        }
+        if entry.timestamp > self.timestamp {
+            return None;
+        }
+        Some(entry.timestamp < self.timestamp || self.seen.contains(&entry.to_logcat_line()))
+    }
+}
+
+// The synthetic entry marking a gap in the stream
+pub fn disconnected_entry(serial: Option<&str>, gap: Duration) -> LogEntry {
+    let mut entry = LogEntry::new(
+        Local::now(),
+        'W',
+        "DevInsight",
+        &format!("Disconnected for {}s, logcat restarted", gap.as_secs()),
+    );
+    entry.device = serial.map(str::to_string);
+    entry
+}
+
+// The synthetic entry saying a device's stream has ended for good
+pub fn failed_entry(serial: Option<&str>, error: &DevInsightError) -> LogEntry {
+    let mut entry = LogEntry::new(Local::now(), 'W', "DevInsight", &format!("Stopped reading logs: {}", error));
+    entry.device = serial.map(str::to_string);
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as TimeDelta, TimeZone};
+
+    fn at(millis: i64) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, 21, 10, 23, 45).unwrap() + TimeDelta::milliseconds(millis)
+    }
+
+    fn entry(millis: i64, message: &str) -> LogEntry {
+        let mut entry = LogEntry::new(at(millis), 'I', "Test", message);
+        entry.pid = Some(1234);
+        entry.tid = Some(1234);
+        entry
+    }
+
+    fn point(entries: &[LogEntry]) -> ResumePoint {
+        let mut point = None;
+        for entry in entries {
+            ResumePoint::record(&mut point, entry);
+        }
+        point.unwrap()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn restart_delay_doubles_up_to_the_cap() {
+        assert_eq!(restart_delay(0), RESTART_DELAY);
+        assert_eq!(restart_delay(1), Duration::from_secs(2));
+        assert_eq!(restart_delay(3), Duration::from_secs(8));
+        assert_eq!(restart_delay(5), MAX_RESTART_DELAY);
+        assert_eq!(restart_delay(u32::MAX), MAX_RESTART_DELAY);
+    }
+
+    #[test]
+    fn entries_at_the_resume_time_are_skipped_once_seen() {
+        let point = point(&[entry(0, "older"), entry(678, "first"), entry(678, "second")]);
+        assert_eq!(point.is_duplicate(&entry(0, "older")), Some(true));
+        assert_eq!(point.is_duplicate(&entry(678, "first")), Some(true));
+        assert_eq!(point.is_duplicate(&entry(678, "second")), Some(true));
+        // Logged in the same millisecond but never delivered
+        assert_eq!(point.is_duplicate(&entry(678, "third")), Some(false));
+        assert_eq!(point.is_duplicate(&entry(679, "newer")), None);
+    }
+
+    #[test]
+    fn resume_point_ignores_older_and_unparsed_entries() {
+        let mut unparsed = LogEntry::new(at(5000), '?', "UNKNOWN", "--------- beginning of main");
+        unparsed.pid = None;
+        let point = point(&[entry(678, "latest"), entry(100, "late arrival"), unparsed.clone()]);
+        assert_eq!(point.timestamp, at(678));
+        assert_eq!(point.is_duplicate(&unparsed), Some(false));
+    }
+
+    #[test]
+    fn logcat_args_start_at_the_resume_point() {
+        let point = point(&[entry(678, "latest")]);
+        let start = ["-T", "03-21 10:23:45.678"];
+
+        // The TUI's "-T 50"
+        assert_eq!(
+            point.logcat_args(&args(&["-b", "all", "-v", "threadtime", "-T", "50"])),
+            args(&[start[0], start[1], "-b", "all", "-v", "threadtime"])
+        );
+        // --since, with filterspecs kept at the end
+        assert_eq!(
+            point.logcat_args(&args(&["-b", "main", "-T", "2024-03-21 09:00:00.000", "ActivityManager:I", "*:S"])),
+            args(&[start[0], start[1], "-b", "main", "ActivityManager:I", "*:S"])
+        );
+        assert_eq!(point.logcat_args(&args(&["-t", "100"])), args(&start));
+        assert_eq!(point.logcat_args(&[]), args(&start));
+    }
+
+    #[test]
+    fn disconnect_marker() {
+        let entry = disconnected_entry(Some("emulator-5554"), Duration::from_millis(12_900));
+        assert_eq!((entry.tag.as_str(), entry.priority), ("DevInsight", 'W'));
+        assert_eq!(entry.message, "Disconnected for 12s, logcat restarted");
+        assert_eq!(entry.device.as_deref(), Some("emulator-5554"));
+    }
+}
@@ -0,0 +1,257 @@
+use std::time::Instant;
+use chrono::{DateTime, Duration, Local};
+use crate::tui::LogEntry;
+
+// Speeds offered by the TUI's < and > keys
+const SPEEDS: &[f64] = &[0.5, 1.0, 2.0, 10.0];
+
+// How far Left/Right jump in session time
+pub const SEEK_STEP: Duration = Duration::seconds(10);
+
+// Plays a recorded session back into the TUI using the gaps between entry timestamps,
+// with a session clock that can be sped up, paused and moved.
+pub struct Replay {
+    entries: Vec<LogEntry>,
+    position: usize,  // Next entry to emit
+    clock: DateTime<Local>,
+    last_tick: Instant,
+    speed: f64,
+    paused: bool,
+}
+
+impl Replay {
+    pub fn new(mut entries: Vec<LogEntry>, speed: f64) -> Self {
+        // Buffers are interleaved slightly out of order in captures; the stable sort keeps
+        // entries with equal timestamps in the order they were recorded
+        entries.sort_by_key(|entry| entry.timestamp);
+        let clock = entries.first().map_or_else(Local::now, |entry| entry.timestamp);
+        Self {
+            entries,
+            position: 0,
+            clock,
+            last_tick: Instant::now(),
+            speed,
+            paused: false,
+        }
+    }
+
+    // Advance the session clock by the real time since the last tick and return the
+    // entries that have become due
+    pub fn tick(&mut self) -> Vec<LogEntry> {
+        self.tick_at(Instant::now())
+    }
+
+    fn tick_at(&mut self, now: Instant) -> Vec<LogEntry> {
+        let elapsed = now.saturating_duration_since(self.last_tick);
+        self.last_tick = now;
+        if !self.paused {
+            let session_elapsed = elapsed.as_secs_f64() * self.speed;
+            self.clock += Duration::microseconds((session_elapsed * 1_000_000.0) as i64);
+        }
+
+        let due = self.entries[self.position..].partition_point(|entry| entry.timestamp <= self.clock);
+        let start = self.position;
+        self.position += due;
+        self.entries[start..self.position].to_vec()
+    }
+
+    // Move the session clock. Returns true when it moved backwards, in which case
+    // playback restarts from the first entry and the caller should discard what it shows;
+    // the next tick then re-emits everything up to the new position at once.
+    pub fn seek(&mut self, offset: Duration) -> bool {
+        let (Some(first), Some(last)) = (self.entries.first(), self.entries.last()) else {
+            return false;
+        };
+        self.clock = (self.clock + offset).clamp(first.timestamp, last.timestamp);
+        if offset < Duration::zero() {
+            self.position = 0;
+            return true;
+        }
+        false
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = SPEEDS.iter().copied().find(|&s| s > self.speed).unwrap_or(self.speed);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = SPEEDS.iter().rev().copied().find(|&s| s < self.speed).unwrap_or(self.speed);
+    }
+
+    // "▶ 2x 01:23/10:00" for the status bar
+    pub fn status(&self) -> String {
+        let state = if self.paused {
+            "⏸"
+        } else if self.position == self.entries.len() {
+            "⏹"
+        } else {
+            "▶"
+        };
+        let start = self.entries.first().map_or(self.clock, |entry| entry.timestamp);
+        let end = self.entries.last().map_or(self.clock, |entry| entry.timestamp);
+        format!(
+            "{} {}x {}/{}",
+            state,
+            self.speed,
+            format_duration(self.clock - start),
+            format_duration(end - start)
+        )
+    }
+}
+
+// Sleeps between entries so standard mode prints a session with its original timing
+pub struct Pacer {
+    speed: f64,
+    last: Option<DateTime<Local>>,
+}
+
+impl Pacer {
+    pub fn new(speed: f64) -> Self {
+        Self { speed, last: None }
+    }
+
+    pub fn wait_for(&mut self, entry: &LogEntry) {
+        if let Some(delay) = self.delay(entry) {
+            std::thread::sleep(delay);
+        }
+    }
+
+    // How long to wait before showing the entry
+    fn delay(&mut self, entry: &LogEntry) -> Option<std::time::Duration> {
+        let delay = self.last
+            .and_then(|last| (entry.timestamp - last).to_std().ok())
+            .map(|gap| gap.div_f64(self.speed));
+        // Entries that step back in time play immediately without moving the clock back
+        self.last = Some(self.last.map_or(entry.timestamp, |last| last.max(entry.timestamp)));
+        delay
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration as StdDuration;
+
+    // One entry per second of session time, from second 0 to second 30
+    fn session(speed: f64) -> (Replay, Instant) {
+        let start = Local::now();
+        let entries = (0..=30)
+            .rev()
+            .map(|second| LogEntry::new(start + Duration::seconds(second), 'I', "Test", &second.to_string()))
+            .collect();
+        let mut replay = Replay::new(entries, speed);
+        let now = Instant::now();
+        replay.last_tick = now;
+        (replay, now)
+    }
+
+    fn messages(entries: Vec<LogEntry>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.message).collect()
+    }
+
+    fn seconds(range: std::ops::RangeInclusive<i64>) -> Vec<String> {
+        range.map(|second| second.to_string()).collect()
+    }
+
+    #[test]
+    fn tick_follows_original_timing() {
+        let (mut replay, now) = session(1.0);
+        assert_eq!(messages(replay.tick_at(now)), seconds(0..=0));
+        assert_eq!(messages(replay.tick_at(now + StdDuration::from_millis(2500))), seconds(1..=2));
+        assert!(replay.tick_at(now + StdDuration::from_millis(2900)).is_empty());
+        assert_eq!(messages(replay.tick_at(now + StdDuration::from_secs(3))), seconds(3..=3));
+    }
+
+    #[test]
+    fn tick_scales_by_speed() {
+        let (mut replay, now) = session(10.0);
+        assert_eq!(messages(replay.tick_at(now + StdDuration::from_secs(1))), seconds(0..=10));
+
+        let (mut replay, now) = session(0.5);
+        assert_eq!(messages(replay.tick_at(now + StdDuration::from_secs(4))), seconds(0..=2));
+    }
+
+    #[test]
+    fn pause_stops_the_clock() {
+        let (mut replay, now) = session(1.0);
+        replay.tick_at(now + StdDuration::from_secs(1));
+        replay.toggle_pause();
+        assert!(replay.tick_at(now + StdDuration::from_secs(60)).is_empty());
+        assert!(replay.status().starts_with("⏸ 1x 00:01/00:30"));
+
+        // Time spent paused isn't caught up on after resuming
+        replay.toggle_pause();
+        assert!(replay.tick_at(now + StdDuration::from_secs(60)).is_empty());
+        assert_eq!(messages(replay.tick_at(now + StdDuration::from_secs(61))), seconds(2..=2));
+    }
+
+    #[test]
+    fn seek_forward_emits_what_it_skips() {
+        let (mut replay, now) = session(1.0);
+        replay.tick_at(now);
+        assert!(!replay.seek(SEEK_STEP));
+        assert_eq!(messages(replay.tick_at(now)), seconds(1..=10));
+
+        // Clamped to the end of the session
+        replay.seek(Duration::minutes(5));
+        assert_eq!(messages(replay.tick_at(now)), seconds(11..=30));
+        assert!(replay.status().starts_with("⏹ 1x 00:30/00:30"));
+    }
+
+    #[test]
+    fn seek_backward_restarts_and_re_emits() {
+        let (mut replay, now) = session(1.0);
+        replay.tick_at(now + StdDuration::from_secs(15));
+        assert!(replay.seek(-SEEK_STEP));
+        assert_eq!(messages(replay.tick_at(now + StdDuration::from_secs(15))), seconds(0..=5));
+
+        // Clamped to the start of the session
+        assert!(replay.seek(Duration::minutes(-5)));
+        assert_eq!(messages(replay.tick_at(now + StdDuration::from_secs(15))), seconds(0..=0));
+    }
+
+    #[test]
+    fn speed_steps() {
+        let (mut replay, _) = session(1.0);
+        replay.faster();
+        assert_eq!(replay.speed, 2.0);
+        replay.faster();
+        replay.faster();
+        assert_eq!(replay.speed, 10.0);
+        replay.slower();
+        replay.slower();
+        replay.slower();
+        replay.slower();
+        assert_eq!(replay.speed, 0.5);
+
+        // A --speed between the steps moves to the neighbouring ones
+        let (mut replay, _) = session(3.0);
+        replay.slower();
+        assert_eq!(replay.speed, 2.0);
+    }
+
+    #[test]
+    fn pacer_waits_for_the_scaled_gap() {
+        let start = Local::now();
+        let at = |millis| LogEntry::new(start + Duration::milliseconds(millis), 'I', "Test", "");
+        let mut pacer = Pacer::new(2.0);
+        assert_eq!(pacer.delay(&at(0)), None);
+        assert_eq!(pacer.delay(&at(1000)), Some(StdDuration::from_millis(500)));
+        // Stepping back plays at once, and the next gap counts from the latest time seen
+        assert_eq!(pacer.delay(&at(400)), None);
+        assert_eq!(pacer.delay(&at(1200)), Some(StdDuration::from_millis(100)));
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::crash::{StackFrame, CRASH_TAG};
+use crate::tui::LogEntry;
+use crate::DevInsightError;
+
+// One method line from an R8/ProGuard mapping file:
+// "    1:3:void bar(int):10:12 -> a"
+struct MethodMapping {
+    obfuscated_range: Option<(u32, u32)>,
+    original_class: Option<String>,  // Set when the method was inlined from another class
+    original_name: String,
+    original_range: Option<(u32, Option<u32>)>,
+}
+
+impl MethodMapping {
+    fn covers(&self, line: u32) -> bool {
+        self.obfuscated_range.is_some_and(|(start, end)| start <= line && line <= end)
+    }
+
+    fn original_line(&self, line: Option<u32>) -> Option<u32> {
+        match (self.obfuscated_range, self.original_range, line) {
+            // A single original line marks the call site of an inlined method
+            (_, Some((start, None)), _) => Some(start),
+            (Some((obf_start, _)), Some((start, Some(end))), Some(line)) if end != start => {
+                Some(start + line.saturating_sub(obf_start))
+            }
+            (_, Some((start, Some(_))), _) => Some(start),
+            (_, None, line) => line,
+        }
+    }
+}
+
+struct ClassMapping {
+    original: String,
+    methods: HashMap<String, Vec<MethodMapping>>,
+}
+
+// Deobfuscates Java stack traces using an R8/ProGuard mapping.txt
+pub struct Retracer {
+    classes: HashMap<String, ClassMapping>,  // Keyed by obfuscated name
+    source_files: HashMap<String, String>,   // Original class -> source file from R8 metadata
+}
+
+impl Retracer {
+    pub fn load(path: &Path) -> Result<Self, DevInsightError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| DevInsightError::MappingError(format!("{}: {}", path.display(), e)))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, DevInsightError> {
+        let mut classes = HashMap::new();
+        let mut source_files = HashMap::new();
+        let mut current: Option<(String, ClassMapping)> = None;
+
+        for (number, line) in text.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(comment) = trimmed.strip_prefix('#') {
+                // R8 attaches '# {"id":"sourceFile","fileName":"Foo.kt"}' to the class above it
+                if let (Some((_, class)), Ok(meta)) = (&current, serde_json::from_str::<serde_json::Value>(comment.trim())) {
+                    if meta["id"] == "sourceFile" {
+                        if let Some(file) = meta["fileName"].as_str() {
+                            source_files.insert(class.original.clone(), file.to_string());
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let invalid = || DevInsightError::MappingError(format!("line {}: unrecognised mapping '{}'", number + 1, trimmed));
+            let (original, obfuscated) = trimmed.split_once(" -> ").ok_or_else(invalid)?;
+
+            if !line.starts_with(char::is_whitespace) {
+                let obfuscated = obfuscated.strip_suffix(':').ok_or_else(invalid)?;
+                if let Some((name, class)) = current.take() {
+                    classes.insert(name, class);
+                }
+                current = Some((obfuscated.to_string(), ClassMapping {
+                    original: original.to_string(),
+                    methods: HashMap::new(),
+                }));
+            } else if original.contains('(') {
+                let method = parse_method(original).ok_or_else(invalid)?;
+                if let Some((_, class)) = &mut current {
+                    class.methods.entry(obfuscated.to_string()).or_default().push(method);
+                }
+            }
+            // Field mappings don't appear in stack traces, so they're skipped
+        }
+
+        if let Some((name, class)) = current.take() {
+            classes.insert(name, class);
+        }
+        Ok(Self { classes, source_files })
+    }
+
+    // Retrace one frame. Inlined methods expand into several frames, innermost first.
+    pub fn retrace_frame(&self, frame: &StackFrame) -> Vec<StackFrame> {
+        let Some(class) = self.classes.get(&frame.class) else {
+            return vec![frame.clone()];
+        };
+
+        let candidates = class.methods.get(&frame.method).map(Vec::as_slice).unwrap_or_default();
+        let in_range: Vec<&MethodMapping> = match frame.line {
+            Some(line) => candidates.iter().filter(|m| m.covers(line)).collect(),
+            None => Vec::new(),
+        };
+
+        if !in_range.is_empty() {
+            return in_range
+                .into_iter()
+                .map(|method| {
+                    let original_class = method.original_class.as_deref().unwrap_or(&class.original);
+                    StackFrame {
+                        class: original_class.to_string(),
+                        method: method.original_name.clone(),
+                        file: self.source_file(original_class, frame.file.as_deref()),
+                        line: method.original_line(frame.line),
+                    }
+                })
+                .collect();
+        }
+
+        // Without a matching line range, list every original name the method could be
+        let names = distinct_names(candidates);
+        let method = if names.is_empty() { frame.method.clone() } else { names.join("|") };
+
+        vec![StackFrame {
+            class: class.original.clone(),
+            method,
+            file: self.source_file(&class.original, frame.file.as_deref()),
+            line: frame.line,
+        }]
+    }
+
+    // Only stack trace content is touched; elsewhere a dotted word like a version number
+    // could collide with an obfuscated class name. Every line of a crash record belongs to it.
+    pub fn retrace_entry(&self, entry: &mut LogEntry) {
+        if let Some(message) = self.retrace_message(&entry.message, entry.tag == CRASH_TAG) {
+            entry.message = message;
+        }
+    }
+
+    // Retrace every frame and obfuscated class name in a (possibly multi-line) message.
+    // Returns None when nothing needed changing.
+    pub fn retrace_message(&self, message: &str, crash: bool) -> Option<String> {
+        let mut changed = false;
+        let lines: Vec<String> = message
+            .lines()
+            .map(|line| {
+                let retraced = self.retrace_line(line, crash);
+                changed |= retraced != line;
+                retraced
+            })
+            .collect();
+        changed.then(|| lines.join("\n"))
+    }
+
+    fn retrace_line(&self, line: &str, crash: bool) -> String {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        if let Some(frame) = line.trim().strip_prefix("at ").and_then(StackFrame::parse) {
+            return self.retrace_frame(&frame)
+                .iter()
+                .map(|frame| format!("{}{}", indent, frame))
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        if crash || is_trace_header(line.trim()) {
+            self.retrace_class_names(line)
+        } else {
+            line.to_string()
+        }
+    }
+
+    // Replace qualified obfuscated class names ("a.b.c", or "a.b.c.d" for a method on a.b.c)
+    // anywhere in a trace line, including NPE messages after the exception name.
+    fn retrace_class_names(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut token = String::new();
+        for c in text.chars().chain(std::iter::once('\0')) {
+            if c.is_alphanumeric() || c == '_' || c == '$' || c == '.' {
+                token.push(c);
+                continue;
+            }
+            result.push_str(&self.retrace_token(&token));
+            token.clear();
+            if c != '\0' {
+                result.push(c);
+            }
+        }
+        result
+    }
+
+    fn retrace_token(&self, token: &str) -> String {
+        let trimmed = token.trim_end_matches('.');
+        let suffix = &token[trimmed.len()..];
+        if !trimmed.contains('.') {
+            return token.to_string();
+        }
+
+        if let Some(class) = self.classes.get(trimmed) {
+            return format!("{}{}", class.original, suffix);
+        }
+
+        if let Some((class_name, member)) = trimmed.rsplit_once('.') {
+            if let Some(class) = self.classes.get(class_name) {
+                let names = distinct_names(class.methods.get(member).map(Vec::as_slice).unwrap_or_default());
+                let member = if names.len() == 1 { names[0] } else { member };
+                return format!("{}.{}{}", class.original, member, suffix);
+            }
+        }
+
+        token.to_string()
+    }
+
+    fn source_file(&self, original_class: &str, obfuscated_file: Option<&str>) -> Option<String> {
+        if let Some(file) = self.source_files.get(original_class) {
+            return Some(file.clone());
+        }
+        match obfuscated_file {
+            Some(file) if file != "SourceFile" && file != "Unknown Source" => Some(file.to_string()),
+            _ => {
+                // Fall back to the outermost class name, as retrace does
+                let simple = original_class.rsplit('.').next().unwrap_or(original_class);
+                let outer = simple.split('$').next().unwrap_or(simple);
+                Some(format!("{}.java", outer))
+            }
+        }
+    }
+}
+
+// "Caused by: a.b.c: boom", "Suppressed: ..." or an exception header like "a.b.c: boom"
+fn is_trace_header(line: &str) -> bool {
+    if line.starts_with("Caused by: ") || line.starts_with("Suppressed: ") {
+        return true;
+    }
+    let exception = line.split_once(':').map_or(line, |(exception, _)| exception);
+    exception.contains('.')
+        && exception.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$' || c == '.')
+}
+
+fn distinct_names(methods: &[MethodMapping]) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    for method in methods {
+        if !names.contains(&method.original_name.as_str()) {
+            names.push(&method.original_name);
+        }
+    }
+    names
+}
+
+// "1:3:void bar(int):10:12", "void baz()" or "5:5:void com.other.Util.helper():30:30"
+fn parse_method(text: &str) -> Option<MethodMapping> {
+    let mut rest = text;
+    let mut obfuscated_range = None;
+    if rest.starts_with(|c: char| c.is_ascii_digit()) {
+        let mut parts = rest.splitn(3, ':');
+        let start = parts.next()?.parse().ok()?;
+        let end = parts.next()?.parse().ok()?;
+        obfuscated_range = Some((start, end));
+        rest = parts.next()?;
+    }
+
+    let open = rest.find('(')?;
+    let close = open + rest[open..].find(')')?;
+    let (_, qualified_name) = rest[..open].trim().rsplit_once(' ')?;
+
+    let original_range = match rest[close + 1..].strip_prefix(':') {
+        Some(range) => {
+            let mut parts = range.split(':');
+            let start = parts.next()?.parse().ok()?;
+            let end = parts.next().and_then(|e| e.parse().ok());
+            Some((start, end))
+        }
+        None => None,
+    };
+
+    let (original_class, original_name) = match qualified_name.rsplit_once('.') {
+        Some((class, name)) => (Some(class.to_string()), name.to_string()),
+        None => (None, qualified_name.to_string()),
+    };
+
+    Some(MethodMapping {
+        obfuscated_range,
+        original_class,
+        original_name,
+        original_range,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAPPING: &str = "\
+# compiler: R8
+com.example.Foo -> a.b.c:
+# {\"id\":\"sourceFile\",\"fileName\":\"Foo.kt\"}
+    int count -> a
+    1:3:void bar(int):10:12 -> a
+    4:4:void com.example.Util.helper():30:30 -> b
+    4:4:void baz():20:20 -> b
+    void reset() -> c
+    void clear() -> c
+com.example.Foo$Inner -> a.b.d:
+";
+
+    fn retracer() -> Retracer {
+        Retracer::parse(MAPPING).unwrap()
+    }
+
+    fn frame(text: &str) -> StackFrame {
+        StackFrame::parse(text).unwrap()
+    }
+
+    fn frames(frames: &[StackFrame]) -> Vec<String> {
+        frames.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn parse_reads_classes_methods_and_source_files() {
+        let retracer = retracer();
+        assert_eq!(retracer.classes["a.b.c"].original, "com.example.Foo");
+        assert_eq!(retracer.classes["a.b.d"].original, "com.example.Foo$Inner");
+        assert_eq!(retracer.classes["a.b.c"].methods["b"].len(), 2);
+        assert!(!retracer.classes["a.b.c"].methods.contains_key("count"));
+        assert_eq!(retracer.source_files["com.example.Foo"], "Foo.kt");
+    }
+
+    #[test]
+    fn parse_rejects_malformed_lines() {
+        assert!(Retracer::parse("com.example.Foo a.b.c").is_err());
+        assert!(Retracer::parse("com.example.Foo -> a.b.c").is_err());
+        assert!(Retracer::parse("a -> b:\n    1:x:void bar() -> a").is_err());
+    }
+
+    #[test]
+    fn retrace_frame_maps_line_ranges() {
+        let retraced = retracer().retrace_frame(&frame("at a.b.c.a(SourceFile:2)"));
+        assert_eq!(frames(&retraced), ["at com.example.Foo.bar(Foo.kt:11)"]);
+    }
+
+    #[test]
+    fn retrace_frame_expands_inlined_methods() {
+        let retraced = retracer().retrace_frame(&frame("at a.b.c.b(SourceFile:4)"));
+        assert_eq!(frames(&retraced), [
+            "at com.example.Util.helper(Util.java:30)",
+            "at com.example.Foo.baz(Foo.kt:20)",
+        ]);
+    }
+
+    #[test]
+    fn retrace_frame_lists_ambiguous_names() {
+        let retraced = retracer().retrace_frame(&frame("at a.b.c.c(Unknown Source)"));
+        assert_eq!(frames(&retraced), ["at com.example.Foo.reset|clear(Foo.kt)"]);
+    }
+
+    #[test]
+    fn retrace_frame_leaves_unknown_classes() {
+        let retraced = retracer().retrace_frame(&frame("at android.os.Looper.loop(Looper.java:288)"));
+        assert_eq!(frames(&retraced), ["at android.os.Looper.loop(Looper.java:288)"]);
+    }
+
+    #[test]
+    fn retrace_message_rewrites_trace_lines() {
+        let message = "a.b.d: boom\n\tat a.b.c.a(SourceFile:1)\nCaused by: java.lang.IllegalStateException: a.b.c.a failed";
+        assert_eq!(
+            retracer().retrace_message(message, false).unwrap(),
+            "com.example.Foo$Inner: boom\n\tat com.example.Foo.bar(Foo.kt:10)\n\
+             Caused by: java.lang.IllegalStateException: com.example.Foo.bar failed"
+        );
+    }
+
+    #[test]
+    fn retrace_message_leaves_other_text() {
+        let retracer = retracer();
+        assert_eq!(retracer.retrace_message("version a.b.c", false), None);
+        assert_eq!(retracer.retrace_message("Loaded a.b.d in 3ms", false), None);
+        assert_eq!(retracer.retrace_message("at the end", false), None);
+    }
+
+    #[test]
+    fn retrace_message_rewrites_all_crash_lines() {
+        assert_eq!(
+            retracer().retrace_message("Process: com.example, component a.b.d", true).unwrap(),
+            "Process: com.example, component com.example.Foo$Inner"
+        );
+    }
+}
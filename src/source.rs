@@ -0,0 +1,535 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Arc};
+use std::time::Instant;
+use crate::bugreport::Bugreport;
+use crate::coalesce::coalesce_lines;
+use crate::device::AdbBackend;
+use crate::reconnect::{disconnected_entry, failed_entry, restart_delay, ResumePoint, MAX_EMPTY_RESTARTS};
+use crate::storage::{session_files, StoredLog};
+use crate::tui::{ConnectionStatus, LogEntry};
+use crate::DevInsightError;
+
+// Anything that produces parsed log entries: a live device, a capture file, a pipe.
+// Sources are shared between the reader thread (`run`) and the TUI (`describe` and
+// `connection_status`), so they hold only configuration and start their work in `run`.
+pub trait LogSource: Send + Sync {
+    // Short description for the startup banner and status bar
+    fn describe(&self) -> String;
+
+    // Read entries until the source is exhausted, handing each one to `on_entry`
+    fn run(&self, on_entry: &mut dyn FnMut(LogEntry)) -> Result<(), DevInsightError>;
+
+    // Whether the device behind the source is reachable; None for sources without one
+    fn connection_status(&self) -> Option<ConnectionStatus> {
+        None
+    }
+
+    // Details about the device that produced the logs, for the TUI header
+    fn header(&self) -> Option<String> {
+        None
+    }
+
+    // Serial of the single device the source reads, if it has one
+    fn serial(&self) -> Option<String> {
+        None
+    }
+}
+
+// Live `adb logcat` with the given arguments, on one device (or adb's default device).
+// Entries are tagged with the serial so several devices can share one view.
+pub struct AdbSource {
+    backend: AdbBackend,
+    serial: Option<String>,
+    args: Vec<String>,
+}
+
+impl AdbSource {
+    pub fn new(backend: AdbBackend, serial: Option<String>, args: Vec<String>) -> Self {
+        Self { backend, serial, args }
+    }
+
+    fn is_online(&self) -> bool {
+        matches!(self.backend.get_state(self.serial.as_deref()).as_deref(), Ok("device"))
+    }
+}
+
+impl LogSource for AdbSource {
+    fn describe(&self) -> String {
+        match &self.serial {
+            Some(serial) => format!("adb -s {} logcat {}", serial, self.args.join(" ")),
+            None => format!("adb logcat {}", self.args.join(" ")),
+        }
+    }
+
+    // Supervises logcat: when it exits (cable blip, reboot, adb restart) wait for the
+    // device, resume from the last delivered timestamp and mark the gap in the stream.
+    // A logcat that exits while the device stays online is restarted with a growing delay.
+    fn run(&self, on_entry: &mut dyn FnMut(LogEntry)) -> Result<(), DevInsightError> {
+        let serial = self.serial.as_deref();
+        let mut resume_point: Option<ResumePoint> = None;
+        let mut restarted = false;
+        let mut empty_restarts = 0;
+
+        loop {
+            let args = match &resume_point {
+                Some(point) if restarted => point.logcat_args(&self.args),
+                _ => self.args.clone(),
+            };
+            let (output, process) = self.backend.logcat(serial, &args)?;
+
+            let mut dedup = resume_point.clone().filter(|_| restarted);
+            let mut received = false;
+            coalesce_lines(output, |mut entry| {
+                entry.device = self.serial.clone();
+                if let Some(point) = &dedup {
+                    match point.is_duplicate(&entry) {
+                        Some(true) => return,
+                        Some(false) => {}
+                        None => dedup = None,
+                    }
+                }
+                received = true;
+                ResumePoint::record(&mut resume_point, &entry);
+                on_entry(entry);
+            })?;
+
+            // A logcat that can't start at all (no device, bad arguments) is an error
+            // rather than something to wait out
+            let mut exit = process.wait().err();
+            if !restarted && !received {
+                if let Some(e) = exit.take() {
+                    return Err(e);
+                }
+            }
+
+            let disconnected_at = Instant::now();
+            let online = self.is_online();
+            if received {
+                empty_restarts = 0;
+            } else if online {
+                empty_restarts += 1;
+                if empty_restarts > MAX_EMPTY_RESTARTS {
+                    return Err(exit.unwrap_or_else(|| DevInsightError::LogcatCaptureFailed(format!(
+                        "logcat on {} keeps exiting without output",
+                        serial.unwrap_or("the default device")
+                    ))));
+                }
+            }
+
+            std::thread::sleep(restart_delay(empty_restarts));
+            if !online || !self.is_online() {
+                self.backend.wait_for_device(serial)?;
+                on_entry(disconnected_entry(serial, disconnected_at.elapsed()));
+                empty_restarts = 0;
+            }
+            restarted = true;
+        }
+    }
+
+    fn serial(&self) -> Option<String> {
+        self.serial.clone()
+    }
+
+    fn connection_status(&self) -> Option<ConnectionStatus> {
+        Some(match self.backend.get_state(self.serial.as_deref()) {
+            Ok(state) if state == "device" => ConnectionStatus::Connected,
+            Ok(_) => ConnectionStatus::Disconnected,
+            Err(DevInsightError::AdbNotFound | DevInsightError::AdbServerError(_)) => ConnectionStatus::Error,
+            Err(_) => ConnectionStatus::Disconnected,
+        })
+    }
+}
+
+// Several sources read concurrently into one stream, e.g. one AdbSource per device
+pub struct MultiSource {
+    sources: Vec<Arc<dyn LogSource>>,
+}
+
+impl MultiSource {
+    pub fn new(sources: Vec<Arc<dyn LogSource>>) -> Self {
+        Self { sources }
+    }
+}
+
+impl LogSource for MultiSource {
+    fn describe(&self) -> String {
+        self.sources.iter().map(|s| s.describe()).collect::<Vec<_>>().join(" + ")
+    }
+
+    fn run(&self, on_entry: &mut dyn FnMut(LogEntry)) -> Result<(), DevInsightError> {
+        let (tx, rx) = mpsc::channel();
+        let readers: Vec<_> = self.sources
+            .iter()
+            .map(|source| {
+                let source = source.clone();
+                let tx = tx.clone();
+                std::thread::spawn(move || {
+                    let result = source.run(&mut |entry| {
+                        tx.send(entry).ok();
+                    });
+                    // Say so in the stream right away, as the others may go on for hours
+                    if let Err(e) = &result {
+                        tx.send(failed_entry(source.serial().as_deref(), e)).ok();
+                    }
+                    result
+                })
+            })
+            .collect();
+        drop(tx);
+
+        for entry in rx {
+            on_entry(entry);
+        }
+
+        // One device failing doesn't stop the others; once they've all ended, report the
+        // error of the first source (in the order given) that failed
+        let mut first_error = None;
+        for reader in readers {
+            let result = reader.join().unwrap_or_else(|_| {
+                Err(DevInsightError::LogcatCaptureFailed("log reader panicked".to_string()))
+            });
+            if let Err(e) = result {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    // Connected only while every device is
+    fn connection_status(&self) -> Option<ConnectionStatus> {
+        let statuses: Vec<ConnectionStatus> = self.sources.iter().filter_map(|s| s.connection_status()).collect();
+        if statuses.is_empty() {
+            None
+        } else if statuses.contains(&ConnectionStatus::Error) {
+            Some(ConnectionStatus::Error)
+        } else if statuses.contains(&ConnectionStatus::Disconnected) {
+            Some(ConnectionStatus::Disconnected)
+        } else {
+            Some(ConnectionStatus::Connected)
+        }
+    }
+}
+
+// A saved log: either JSONL written by LogStorage, or plain logcat text in any `-v` format
+pub struct FileSource {
+    path: PathBuf,
+}
+
+impl FileSource {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl LogSource for FileSource {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn run(&self, on_entry: &mut dyn FnMut(LogEntry)) -> Result<(), DevInsightError> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+
+        // Decide on the format from the first non-empty line
+        let is_jsonl = loop {
+            let buf = reader.fill_buf()?;
+            match buf.first() {
+                Some(b) if b.is_ascii_whitespace() => reader.consume(1),
+                Some(&b) => break b == b'{',
+                None => return Ok(()),
+            }
+        };
+
+        if is_jsonl {
+            read_stored_logs(reader, on_entry)
+        } else {
+            coalesce_lines(reader, on_entry)?;
+            Ok(())
+        }
+    }
+}
+
+// A session saved with --save: one JSONL file, or a directory of rotated files read in order
+pub struct SessionSource {
+    path: PathBuf,
+}
+
+impl SessionSource {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl LogSource for SessionSource {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn run(&self, on_entry: &mut dyn FnMut(LogEntry)) -> Result<(), DevInsightError> {
+        let files = session_files(&self.path)?;
+        if files.is_empty() {
+            return Err(DevInsightError::StorageError(format!("no saved logs in {}", self.path.display())));
+        }
+        for file in files {
+            let reader = File::open(&file)
+                .map_err(|e| DevInsightError::StorageError(format!("{}: {}", file.display(), e)))?;
+            read_stored_logs(BufReader::new(reader), on_entry)?;
+        }
+        Ok(())
+    }
+}
+
+// The SYSTEM/EVENT/RADIO LOG sections and tombstones of a bugreport. The file is read
+// when the source is created so a bad path fails before the TUI starts.
+pub struct BugreportSource {
+    path: PathBuf,
+    bugreport: Bugreport,
+}
+
+impl BugreportSource {
+    pub fn open(path: &Path) -> Result<Self, DevInsightError> {
+        Ok(Self { path: path.to_path_buf(), bugreport: Bugreport::open(path)? })
+    }
+}
+
+impl LogSource for BugreportSource {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn run(&self, on_entry: &mut dyn FnMut(LogEntry)) -> Result<(), DevInsightError> {
+        self.bugreport.entries(on_entry);
+        Ok(())
+    }
+
+    fn header(&self) -> Option<String> {
+        self.bugreport.header()
+    }
+}
+
+// Logcat text piped into DevInsight, e.g. `adb logcat -d | devinsight --stdin`
+pub struct StdinSource;
+
+impl LogSource for StdinSource {
+    fn describe(&self) -> String {
+        "stdin".to_string()
+    }
+
+    fn run(&self, on_entry: &mut dyn FnMut(LogEntry)) -> Result<(), DevInsightError> {
+        coalesce_lines(BufReader::new(std::io::stdin()), on_entry)?;
+        Ok(())
+    }
+}
+
+// Any shell command whose stdout is logcat text, e.g. `ssh ci-host cat build/logcat.txt`
+pub struct CommandSource {
+    command: String,
+}
+
+impl CommandSource {
+    pub fn new(command: String) -> Self {
+        Self { command }
+    }
+}
+
+impl LogSource for CommandSource {
+    fn describe(&self) -> String {
+        self.command.clone()
+    }
+
+    fn run(&self, on_entry: &mut dyn FnMut(LogEntry)) -> Result<(), DevInsightError> {
+        let mut process = Command::new("sh")
+            .args(["-c", &self.command])
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| DevInsightError::LogcatCaptureFailed(format!("{}: {}", self.command, e)))?;
+        let stdout = process.stdout
+            .take()
+            .ok_or(DevInsightError::LogcatCaptureFailed("Failed to capture stdout".to_string()))?;
+        coalesce_lines(BufReader::new(stdout), on_entry)?;
+
+        // A failing command would otherwise look like an empty log
+        let status = process.wait()?;
+        if !status.success() {
+            return Err(DevInsightError::LogcatCaptureFailed(format!("{}: {}", self.command, status)));
+        }
+        Ok(())
+    }
+}
+
+// Replay StoredLog lines, skipping any that don't parse (e.g. a line cut off by a crash)
+fn read_stored_logs<R: BufRead>(reader: R, on_entry: &mut dyn FnMut(LogEntry)) -> Result<(), DevInsightError> {
+    for line in reader.lines() {
+        let line = line?;
+        if let Ok(log) = serde_json::from_str::<StoredLog>(&line) {
+            on_entry(log.into_entry());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use chrono::Local;
+
+    // Emits its messages, then fails if it has an error to report
+    struct FakeSource {
+        messages: Vec<&'static str>,
+        error: Option<&'static str>,
+    }
+
+    impl LogSource for FakeSource {
+        fn describe(&self) -> String {
+            "fake".to_string()
+        }
+
+        fn run(&self, on_entry: &mut dyn FnMut(LogEntry)) -> Result<(), DevInsightError> {
+            for message in &self.messages {
+                on_entry(LogEntry::new(Local::now(), 'I', "Fake", message));
+            }
+            match self.error {
+                Some(error) => Err(DevInsightError::LogcatCaptureFailed(error.to_string())),
+                None => Ok(()),
+            }
+        }
+    }
+
+    fn stored_line(message: &str) -> String {
+        let entry = LogEntry::new(Local::now(), 'W', "Stored", message);
+        serde_json::to_string(&StoredLog::from_entry(&entry)).unwrap()
+    }
+
+    fn collect(source: &dyn LogSource) -> (Vec<LogEntry>, Result<(), DevInsightError>) {
+        let mut entries = Vec::new();
+        let result = source.run(&mut |entry| entries.push(entry));
+        (entries, result)
+    }
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("devinsight-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn file_source_reads_jsonl() {
+        let path = temp_file("saved.jsonl", &format!("\n  {}\n{}\n", stored_line("one"), stored_line("two")));
+        let (entries, result) = collect(&FileSource::new(path.clone()));
+        std::fs::remove_file(path).ok();
+
+        assert!(result.is_ok());
+        let messages: Vec<&str> = entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["one", "two"]);
+        assert_eq!(entries[0].tag, "Stored");
+    }
+
+    #[test]
+    fn file_source_reads_logcat_text() {
+        let text = "03-21 10:23:45.678  1234  1234 I ActivityManager: Start proc\n\
+                    03-21 10:23:45.679  1234  1234 E AndroidRuntime: FATAL EXCEPTION: main\n";
+        let path = temp_file("saved.txt", text);
+        let (entries, result) = collect(&FileSource::new(path.clone()));
+        std::fs::remove_file(path).ok();
+
+        assert!(result.is_ok());
+        let tags: Vec<&str> = entries.iter().map(|e| e.tag.as_str()).collect();
+        assert_eq!(tags, ["ActivityManager", "AndroidRuntime"]);
+        assert_eq!(entries[0].pid, Some(1234));
+    }
+
+    #[test]
+    fn file_source_accepts_empty_files() {
+        let path = temp_file("empty.txt", "\n\n");
+        let (entries, result) = collect(&FileSource::new(path.clone()));
+        std::fs::remove_file(path).ok();
+
+        assert!(result.is_ok());
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn session_source_reads_files_oldest_first() {
+        let dir = std::env::temp_dir().join(format!("devinsight-{}-sessionsource", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let line = |message: &str, age: i64| {
+            let entry = LogEntry::new(Local::now() - chrono::Duration::minutes(age), 'I', "Stored", message);
+            serde_json::to_string(&StoredLog::from_entry(&entry)).unwrap()
+        };
+        std::fs::write(dir.join("logcat_1.jsonl"), format!("{}\n{}\n", line("newer", 5), line("newest", 1))).unwrap();
+        std::fs::write(dir.join("logcat_2.jsonl"), format!("{}\n", line("oldest", 10))).unwrap();
+        let (entries, result) = collect(&SessionSource::new(dir.clone()));
+        let (_, empty) = collect(&SessionSource::new(dir.join("missing")));
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(result.is_ok());
+        let messages: Vec<&str> = entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["oldest", "newer", "newest"]);
+        assert!(empty.is_err());
+    }
+
+    #[test]
+    fn stored_logs_skip_truncated_lines() {
+        let complete = stored_line("complete");
+        let truncated = &stored_line("cut off")[..20];
+        let text = format!("{}\n{}\n{}\n", complete, truncated, stored_line("after"));
+
+        let mut messages = Vec::new();
+        read_stored_logs(Cursor::new(text), &mut |entry| messages.push(entry.message)).unwrap();
+        assert_eq!(messages, ["complete", "after"]);
+    }
+
+    #[test]
+    fn multi_source_forwards_entries_and_reports_first_error() {
+        let source = MultiSource::new(vec![
+            Arc::new(FakeSource { messages: vec!["a1"], error: None }),
+            Arc::new(FakeSource { messages: vec!["b1", "b2"], error: Some("first") }),
+            Arc::new(FakeSource { messages: vec!["c1"], error: Some("second") }),
+        ]);
+        let (entries, result) = collect(&source);
+
+        let mut messages: Vec<&str> = entries.iter().filter(|e| e.tag == "Fake").map(|e| e.message.as_str()).collect();
+        messages.sort();
+        assert_eq!(messages, ["a1", "b1", "b2", "c1"]);
+        assert_eq!(entries.iter().filter(|e| e.tag == "DevInsight").count(), 2);
+        match result {
+            Err(DevInsightError::LogcatCaptureFailed(message)) => assert_eq!(message, "first"),
+            other => panic!("expected the first error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn multi_source_reports_a_failed_source_in_the_stream() {
+        let source = MultiSource::new(vec![
+            Arc::new(FakeSource { messages: vec!["a1"], error: Some("gave up") }),
+        ]);
+        let (entries, result) = collect(&source);
+
+        assert!(result.is_err());
+        let last = entries.last().unwrap();
+        assert_eq!((last.tag.as_str(), last.priority), ("DevInsight", 'W'));
+        assert!(last.message.contains("gave up"), "{}", last.message);
+    }
+
+    #[test]
+    fn command_source_reads_stdout() {
+        let source = CommandSource::new("printf '03-21 10:23:45.678  1234  1234 I Cmd: hello\\n'".to_string());
+        let (entries, result) = collect(&source);
+        assert!(result.is_ok());
+        assert_eq!((entries[0].tag.as_str(), entries[0].message.as_str()), ("Cmd", "hello"));
+    }
+
+    #[test]
+    fn command_source_reports_a_failing_command() {
+        let source = CommandSource::new("echo '03-21 10:23:45.678  1234  1234 I Cmd: partial'; exit 3".to_string());
+        let (entries, result) = collect(&source);
+        assert_eq!(entries.len(), 1);
+        match result {
+            Err(DevInsightError::LogcatCaptureFailed(message)) => assert!(message.ends_with("exit status: 3"), "{}", message),
+            other => panic!("expected the exit status, got {:?}", other),
+        }
+    }
+}
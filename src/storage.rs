@@ -1,19 +1,64 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs::{File, OpenOptions, create_dir_all};
 use std::io::{self, Write, BufReader, BufRead};
 use chrono::{DateTime, Local};
 use serde::{Serialize, Deserialize};
 use std::sync::mpsc::Sender;
+use crate::tui::{LogEntry, LogLevel};
+use crate::events::EventField;
 
 #[derive(Serialize, Deserialize)]
 pub struct StoredLog {
     pub timestamp: DateTime<Local>,
-    pub level: String,
+    pub level: LogLevel,
     pub tag: String,
     pub message: String,
+    #[serde(default)]
+    pub uid: Option<String>,
+    #[serde(default)]
+    pub pid: Option<u32>,
+    #[serde(default)]
+    pub tid: Option<u32>,
+    #[serde(default)]
+    pub buffer: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<EventField>,
     pub device_id: Option<String>,
 }
 
+impl StoredLog {
+    pub fn from_entry(entry: &LogEntry) -> Self {
+        Self {
+            timestamp: entry.timestamp,
+            level: entry.level,
+            tag: entry.tag.clone(),
+            message: entry.message.clone(),
+            uid: entry.uid.clone(),
+            pid: entry.pid,
+            tid: entry.tid,
+            buffer: entry.buffer.clone(),
+            fields: entry.fields.clone(),
+            device_id: entry.device.clone(),
+        }
+    }
+
+    pub fn into_entry(self) -> LogEntry {
+        LogEntry {
+            timestamp: self.timestamp,
+            uid: self.uid,
+            pid: self.pid,
+            tid: self.tid,
+            priority: self.level.priority(),
+            level: self.level,
+            tag: self.tag,
+            message: self.message,
+            buffer: self.buffer,
+            fields: self.fields,
+            device: self.device_id,
+        }
+    }
+}
+
 pub struct StorageUpdate {
     pub current_file: String,
     pub total_size: u64,
@@ -56,12 +101,12 @@ impl LogStorage {
         })
     }
 
-    fn generate_filename(base_path: &PathBuf) -> PathBuf {
+    fn generate_filename(base_path: &Path) -> PathBuf {
         let timestamp = Local::now().format("%Y%m%d_%H%M%S");
         base_path.join(format!("logcat_{}.jsonl", timestamp))
     }
 
-    fn get_directory_size(path: &PathBuf) -> io::Result<u64> {
+    fn get_directory_size(path: &Path) -> io::Result<u64> {
         let mut total_size = 0;
         for entry in std::fs::read_dir(path)? {
             let entry = entry?;
@@ -72,7 +117,7 @@ impl LogStorage {
         Ok(total_size)
     }
 
-    fn count_log_files(path: &PathBuf) -> io::Result<usize> {
+    fn count_log_files(path: &Path) -> io::Result<usize> {
         let count = std::fs::read_dir(path)?
             .filter(|entry| {
                 entry.as_ref()
@@ -134,16 +179,129 @@ impl LogStorage {
         for entry in std::fs::read_dir(&self.base_path)? {
             let entry = entry?;
             let reader = BufReader::new(File::open(entry.path())?);
-            for line in reader.lines() {
-                if let Ok(log_str) = line {
-                    if let Ok(log) = serde_json::from_str::<StoredLog>(&log_str) {
-                        if log.timestamp >= start_time && log.timestamp <= end_time {
-                            logs.push(log);
-                        }
+            for log_str in reader.lines().map_while(Result::ok) {
+                if let Ok(log) = serde_json::from_str::<StoredLog>(&log_str) {
+                    if log.timestamp >= start_time && log.timestamp <= end_time {
+                        logs.push(log);
                     }
                 }
             }
         }
         Ok(logs)
     }
-} 
\ No newline at end of file
+}
+
+// The JSONL files making up a saved session, oldest first. A directory is treated as one
+// rotated session: its files are ordered by the first entry they contain, falling back
+// to the file name, which embeds the rotation time.
+pub fn session_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some("jsonl") {
+            let first_timestamp = first_timestamp(&path)?;
+            files.push((first_timestamp, path));
+        }
+    }
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+fn first_timestamp(path: &Path) -> io::Result<Option<DateTime<Local>>> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(reader
+        .lines()
+        .map_while(Result::ok)
+        .find_map(|line| serde_json::from_str::<StoredLog>(&line).ok())
+        .map(|log| log.timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn at(minutes: i64) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, 21, 10, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    fn stored_lines(minutes: &[i64]) -> String {
+        minutes.iter()
+            .map(|&minute| {
+                let entry = LogEntry::new(at(minute), 'I', "Stored", &minute.to_string());
+                serde_json::to_string(&StoredLog::from_entry(&entry)).unwrap() + "\n"
+            })
+            .collect()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("devinsight-{}-{}", std::process::id(), name));
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn names(files: &[PathBuf]) -> Vec<&str> {
+        files.iter().filter_map(|file| file.file_name()?.to_str()).collect()
+    }
+
+    #[test]
+    fn stored_log_round_trips() {
+        let mut entry = LogEntry::new(at(0), 'F', "DEBUG", "*** ***");
+        entry.pid = Some(1234);
+        entry.buffer = Some("crash".to_string());
+        entry.device = Some("emulator-5554".to_string());
+        let json = serde_json::to_string(&StoredLog::from_entry(&entry)).unwrap();
+        let restored = serde_json::from_str::<StoredLog>(&json).unwrap().into_entry();
+        assert_eq!((restored.timestamp, restored.priority, restored.level), (at(0), 'F', LogLevel::Fatal));
+        assert_eq!((restored.pid, restored.buffer.as_deref(), restored.device.as_deref()), (Some(1234), Some("crash"), Some("emulator-5554")));
+    }
+
+    #[test]
+    fn older_lines_without_optional_fields_load() {
+        let json = r#"{"timestamp":"2024-03-21T10:00:00+00:00","level":"ERROR","tag":"Old","message":"saved before pids","device_id":null}"#;
+        let entry = serde_json::from_str::<StoredLog>(json).unwrap().into_entry();
+        assert_eq!((entry.tag.as_str(), entry.pid, entry.priority), ("Old", None, 'E'));
+    }
+
+    #[test]
+    fn session_files_are_ordered_by_first_entry() {
+        let dir = temp_dir("session");
+        // File names would put these the other way round
+        std::fs::write(dir.join("a_merged.jsonl"), stored_lines(&[30, 31])).unwrap();
+        std::fs::write(dir.join("b_rotated.jsonl"), format!("not json\n{}", stored_lines(&[10, 11]))).unwrap();
+        std::fs::write(dir.join("c_first.jsonl"), stored_lines(&[0, 1])).unwrap();
+        std::fs::write(dir.join("empty.jsonl"), "").unwrap();
+        std::fs::write(dir.join("notes.txt"), stored_lines(&[5])).unwrap();
+
+        let files = session_files(&dir);
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(names(&files.unwrap()), ["empty.jsonl", "c_first.jsonl", "b_rotated.jsonl", "a_merged.jsonl"]);
+    }
+
+    #[test]
+    fn single_file_is_its_own_session() {
+        let path = Path::new("/nonexistent/saved.jsonl");
+        assert_eq!(session_files(path).unwrap(), [path.to_path_buf()]);
+    }
+
+    #[test]
+    fn storage_writes_jsonl_with_updates() {
+        let dir = temp_dir("storage");
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut storage = LogStorage::new(dir.clone(), 10, Some(tx)).unwrap();
+        storage.store_log(StoredLog::from_entry(&LogEntry::new(at(0), 'I', "Stored", "0"))).unwrap();
+        let files = session_files(&dir).unwrap();
+        let contents = std::fs::read_to_string(&files[0]).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(contents, stored_lines(&[0]));
+        let update = rx.try_iter().last().unwrap();
+        assert_eq!(update.file_count, 1);
+        assert!(update.total_size > 0);
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use addr2line::Loader;
+use crate::tombstone::NativeFrame;
+use crate::tui::LogEntry;
+use crate::DevInsightError;
+
+// Resolves tombstone `pc` offsets to function names and file:line using the DWARF and
+// ELF symbol tables of unstripped libraries, like ndk-stack.
+pub struct Symbolizer {
+    libraries: HashMap<String, PathBuf>,       // Library file name -> unstripped copy
+    loaders: HashMap<String, Option<Loader>>,  // Loaded lazily; None if the file couldn't be read
+}
+
+impl Symbolizer {
+    pub fn new(dir: &Path) -> Result<Self, DevInsightError> {
+        let mut libraries = HashMap::new();
+        Self::index_libraries(dir, &mut libraries)
+            .map_err(|e| DevInsightError::SymbolError(format!("{}: {}", dir.display(), e)))?;
+        if libraries.is_empty() {
+            return Err(DevInsightError::SymbolError(format!("no .so files found in {}", dir.display())));
+        }
+        Ok(Self {
+            libraries,
+            loaders: HashMap::new(),
+        })
+    }
+
+    // Walk the directory tree (e.g. obj/local/<abi>/ or a merged_native_libs output)
+    fn index_libraries(dir: &Path, libraries: &mut HashMap<String, PathBuf>) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                Self::index_libraries(&path, libraries)?;
+            } else if path.extension().and_then(|ext| ext.to_str()) == Some("so") {
+                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                    libraries.entry(name.to_string()).or_insert(path.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn symbolize_frame(&mut self, frame: &mut NativeFrame) -> bool {
+        let name = library_name(&frame.module).to_string();
+        let Some(path) = self.libraries.get(&name) else {
+            return false;
+        };
+        let loader = self.loaders
+            .entry(name)
+            .or_insert_with(|| Loader::new(path).ok());
+        let Some(loader) = loader else {
+            return false;
+        };
+
+        let mut resolved = false;
+        if let Ok(mut frames) = loader.find_frames(frame.offset) {
+            // Frames run from the innermost (possibly inlined) function out to the one
+            // containing the pc; the location is the innermost one's
+            let mut functions = Vec::new();
+            while let Ok(Some(found)) = frames.next() {
+                if functions.is_empty() {
+                    if let Some(location) = found.location {
+                        frame.file = location.file.map(str::to_string);
+                        frame.line = location.line;
+                        resolved = true;
+                    }
+                }
+                functions.push(found.function.as_ref().and_then(|f| f.demangle().ok()).map(|f| f.into_owned()));
+            }
+
+            if let Some(Some(function)) = functions.first() {
+                set_function(frame, function, functions.len() > 1);
+                resolved = true;
+            }
+        }
+
+        if frame.symbol.is_none() {
+            if let Some(symbol) = loader.find_symbol(frame.offset) {
+                frame.symbol = Some(addr2line::demangle_auto(symbol.into(), None).into_owned());
+                resolved = true;
+            }
+        }
+
+        resolved
+    }
+
+    // Rewrite the backtrace lines of a DEBUG entry so stored logs and crash reports
+    // carry the resolved frames
+    pub fn symbolize_entry(&mut self, entry: &mut LogEntry) {
+        if entry.tag != "DEBUG" || !entry.message.contains(" pc ") {
+            return;
+        }
+
+        let mut changed = false;
+        let lines: Vec<String> = entry.message
+            .lines()
+            .map(|line| {
+                if let Some(mut frame) = NativeFrame::parse(line) {
+                    if self.symbolize_frame(&mut frame) {
+                        changed = true;
+                        let indent = &line[..line.len() - line.trim_start().len()];
+                        return format!("{}{}", indent, frame);
+                    }
+                }
+                line.to_string()
+            })
+            .collect();
+
+        if changed {
+            entry.message = lines.join("\n");
+        }
+    }
+}
+
+// "/data/app/.../base.apk!libfoo.so" and "/system/lib64/libc.so" both resolve by file name
+fn library_name(module: &str) -> &str {
+    module.rsplit(['/', '!']).next().unwrap_or(module)
+}
+
+// The tombstone's offset counts from the start of the function it named, so it stays as
+// long as that's the function resolved here (often just mangled) and nothing was inlined
+fn set_function(frame: &mut NativeFrame, function: &str, inlined: bool) {
+    let named = frame.symbol.as_deref().map(|s| addr2line::demangle_auto(s.into(), None));
+    if inlined || named.as_deref() != Some(function) {
+        frame.symbol_offset = None;
+    }
+    frame.symbol = Some(function.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(line: &str) -> NativeFrame {
+        NativeFrame::parse(line).unwrap_or_else(|| panic!("not a frame: {:?}", line))
+    }
+
+    #[test]
+    fn library_names() {
+        assert_eq!(library_name("/system/lib64/libc.so"), "libc.so");
+        assert_eq!(library_name("/data/app/base.apk!libfoo.so"), "libfoo.so");
+        assert_eq!(library_name("libbare.so"), "libbare.so");
+    }
+
+    #[test]
+    fn offset_kept_for_the_named_function() {
+        let mut mangled = frame("#00 pc 0000000000001105  /data/libfoo.so (_ZN3foo5crashEPi+12)");
+        set_function(&mut mangled, "foo::crash(int*)", false);
+        assert_eq!((mangled.symbol.as_deref(), mangled.symbol_offset), (Some("foo::crash(int*)"), Some(12)));
+
+        let mut same = frame("#00 pc 0000000000001105  /data/libfoo.so (foo::crash(int*)+12)");
+        set_function(&mut same, "foo::crash(int*)", false);
+        assert_eq!(same.symbol_offset, Some(12));
+    }
+
+    #[test]
+    fn offset_dropped_for_another_or_inlined_function() {
+        let mut other = frame("#00 pc 0000000000001105  /data/libfoo.so (exported_entry+400)");
+        set_function(&mut other, "foo::crash(int*)", false);
+        assert_eq!((other.symbol.as_deref(), other.symbol_offset), (Some("foo::crash(int*)"), None));
+
+        let mut inlined = frame("#00 pc 0000000000001105  /data/libfoo.so (foo::crash(int*)+12)");
+        set_function(&mut inlined, "foo::crash(int*)", true);
+        assert_eq!(inlined.symbol_offset, None);
+
+        let mut unnamed = frame("#00 pc 0000000000001105  /data/libfoo.so");
+        set_function(&mut unnamed, "foo::crash(int*)", false);
+        assert_eq!((unnamed.symbol.as_deref(), unnamed.symbol_offset), (Some("foo::crash(int*)"), None));
+    }
+
+    fn fixtures() -> Symbolizer {
+        Symbolizer::new(&Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata")).unwrap()
+    }
+
+    // 0x111c is inside crash_here, on the dereference at crash.c:7
+    #[test]
+    fn symbolize_frame_from_dwarf() {
+        let mut symbolizer = fixtures();
+        let mut crashed = frame("#00 pc 000000000000111c  /data/app/~~x==/lib/arm64/libcrash.so");
+        assert!(symbolizer.symbolize_frame(&mut crashed));
+        assert_eq!(crashed.symbol.as_deref(), Some("crash_here"));
+        assert!(crashed.file.as_deref().is_some_and(|file| file.ends_with("crash.c")));
+        assert_eq!(crashed.line, Some(7));
+
+        let mut unknown = frame("#01 pc 000000000000111c  /system/lib64/libother.so");
+        assert!(!symbolizer.symbolize_frame(&mut unknown));
+        assert_eq!((unknown.symbol, unknown.file), (None, None));
+    }
+
+    #[test]
+    fn symbolize_entry_keeps_the_apk_offset() {
+        let mut symbolizer = fixtures();
+        let mut entry = LogEntry::new(
+            chrono::Local::now(),
+            'F',
+            "DEBUG",
+            "backtrace:\n      #00 pc 000000000000111c  /data/app/base.apk!libcrash.so (offset 0x1000) (crash_here+19)",
+        );
+        symbolizer.symbolize_entry(&mut entry);
+        let (first, rewritten) = entry.message.split_once('\n').unwrap();
+        assert_eq!(first, "backtrace:");
+        assert!(rewritten.starts_with("      #00 pc 000000000000111c  /data/app/base.apk!libcrash.so (offset 0x1000) (crash_here+19) ["));
+        assert!(rewritten.ends_with("crash.c:7]"));
+    }
+
+    #[test]
+    fn symbolizer_needs_libraries() {
+        let dir = std::env::temp_dir().join(format!("devinsight-{}-nolibs", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let result = Symbolizer::new(&dir);
+        std::fs::remove_dir(&dir).ok();
+        assert!(matches!(result, Err(DevInsightError::SymbolError(_))));
+    }
+}
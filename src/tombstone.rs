@@ -0,0 +1,530 @@
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local};
+use crate::block::BlockGrouper;
+use crate::tui::LogEntry;
+
+const DEBUG_TAG: &str = "DEBUG";
+const LIBC_TAG: &str = "libc";
+
+// crash_dump can take a while to unwind, so allow a longer quiet period than Java crashes
+const TOMBSTONE_IDLE: Duration = Duration::from_secs(5);
+
+pub struct NativeFrame {
+    pub index: u32,
+    pub offset: u64,  // pc relative to the start of the module
+    pub module: String,
+    pub apk_offset: Option<u64>,  // Where the library starts inside its APK, for APK-embedded ones
+    pub symbol: Option<String>,
+    pub symbol_offset: Option<u64>,
+    pub build_id: Option<String>,
+    pub file: Option<String>,  // Source location, once symbolicated
+    pub line: Option<u32>,
+}
+
+impl NativeFrame {
+    // "#00 pc 00000000000a1b2c  /data/app/.../libnative.so (crash_here+20) (BuildId: 1a2b...)"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (index, rest) = text.strip_prefix('#')?.split_once(char::is_whitespace)?;
+        let index = index.parse().ok()?;
+        let rest = rest.trim_start().strip_prefix("pc")?.trim_start();
+        let (pc, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let offset = u64::from_str_radix(pc, 16).ok()?;
+
+        let mut rest = rest.trim();
+
+        // Symbolicated frames end with "[native.cpp:42]"
+        let mut file = None;
+        let mut line = None;
+        if let Some(location) = rest.strip_suffix(']').and_then(|r| r.rsplit_once(" [")) {
+            let (path, number) = location.1.rsplit_once(':').unwrap_or((location.1, ""));
+            file = Some(path.to_string());
+            line = number.parse().ok();
+            rest = location.0.trim();
+        }
+
+        let (module, mut rest) = match rest.find(" (") {
+            Some(pos) => (&rest[..pos], rest[pos..].trim()),
+            None => (rest, ""),
+        };
+
+        let mut build_id = None;
+        if let Some(pos) = rest.rfind("(BuildId: ") {
+            build_id = rest[pos + 10..].strip_suffix(')').map(str::to_string);
+            rest = rest[..pos].trim();
+        }
+
+        // APK-embedded libraries carry an "(offset 0x1000)" before the symbol
+        let mut apk_offset = None;
+        if let Some(offset) = rest.strip_prefix("(offset ") {
+            let (value, after) = offset.split_once(')').unwrap_or((offset, ""));
+            apk_offset = u64::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok();
+            rest = after.trim();
+        }
+
+        let (symbol, symbol_offset) = match rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')) {
+            Some(symbol) => match symbol.rsplit_once('+') {
+                Some((name, off)) if off.parse::<u64>().is_ok() => (Some(name.to_string()), off.parse().ok()),
+                _ => (Some(symbol.to_string()), None),
+            },
+            None => (None, None),
+        };
+
+        Some(Self {
+            index,
+            offset,
+            module: module.to_string(),
+            apk_offset,
+            symbol,
+            symbol_offset,
+            build_id,
+            file,
+            line,
+        })
+    }
+}
+
+impl std::fmt::Display for NativeFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02} pc {:016x}  {}", self.index, self.offset, self.module)?;
+        if let Some(offset) = self.apk_offset {
+            write!(f, " (offset {:#x})", offset)?;
+        }
+        match (&self.symbol, self.symbol_offset) {
+            (Some(symbol), Some(off)) => write!(f, " ({}+{})", symbol, off)?,
+            (Some(symbol), None) => write!(f, " ({})", symbol)?,
+            _ => {}
+        }
+        if let Some(build_id) = &self.build_id {
+            write!(f, " (BuildId: {})", build_id)?;
+        }
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, " [{}:{}]", file, line)?,
+            (Some(file), None) => write!(f, " [{}]", file)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+pub struct NativeCrash {
+    pub timestamp: DateTime<Local>,
+    pub process: String,
+    pub pid: Option<u32>,
+    pub tid: Option<u32>,
+    pub thread: String,
+    pub signal: String,
+    pub signal_number: Option<i32>,
+    pub code: String,
+    pub fault_addr: Option<String>,
+    pub abi: String,
+    pub cause: Option<String>,
+    pub fingerprint: Option<String>,
+    pub frames: Vec<NativeFrame>,
+    has_debug_block: bool,
+    in_backtrace: bool,
+    in_other_threads: bool,  // Past the "--- --- ---" separator; those threads didn't crash
+}
+
+impl NativeCrash {
+    fn new(timestamp: DateTime<Local>) -> Self {
+        Self {
+            timestamp,
+            process: String::new(),
+            pid: None,
+            tid: None,
+            thread: String::new(),
+            signal: String::new(),
+            signal_number: None,
+            code: String::new(),
+            fault_addr: None,
+            abi: String::new(),
+            cause: None,
+            fingerprint: None,
+            frames: Vec::new(),
+            has_debug_block: false,
+            in_backtrace: false,
+            in_other_threads: false,
+        }
+    }
+
+    // Render the crash in tombstone layout
+    pub fn report(&self) -> String {
+        let mut report = String::from("*** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***\n");
+        if let Some(fingerprint) = &self.fingerprint {
+            report.push_str(&format!("Build fingerprint: '{}'\n", fingerprint));
+        }
+        if !self.abi.is_empty() {
+            report.push_str(&format!("ABI: '{}'\n", self.abi));
+        }
+        report.push_str(&format!(
+            "pid: {}, tid: {}, name: {}  >>> {} <<<\n",
+            self.pid.map(|p| p.to_string()).unwrap_or_else(|| "?".to_string()),
+            self.tid.map(|t| t.to_string()).unwrap_or_else(|| "?".to_string()),
+            self.thread,
+            self.process
+        ));
+        report.push_str(&format!(
+            "signal {} ({}), code {}, fault addr {}\n",
+            self.signal_number.map(|n| n.to_string()).unwrap_or_else(|| "?".to_string()),
+            self.signal,
+            self.code,
+            self.fault_addr.as_deref().unwrap_or("--------")
+        ));
+        if let Some(cause) = &self.cause {
+            report.push_str(&format!("Cause: {}\n", cause));
+        }
+        report.push_str("\nbacktrace:");
+        for frame in &self.frames {
+            report.push_str(&format!("\n      {}", frame));
+        }
+        report
+    }
+
+    fn apply_debug_line(&mut self, line: &str) {
+        let trimmed = line.trim();
+        self.has_debug_block = true;
+
+        if trimmed.starts_with("--- --- ---") {
+            self.in_other_threads = true;
+        }
+        if self.in_other_threads {
+            return;
+        }
+
+        if self.in_backtrace {
+            if let Some(frame) = NativeFrame::parse(trimmed) {
+                self.frames.push(frame);
+                return;
+            }
+            self.in_backtrace = false;
+        }
+
+        if trimmed == "backtrace:" {
+            self.in_backtrace = true;
+        } else if let Some(fingerprint) = trimmed.strip_prefix("Build fingerprint: ") {
+            self.fingerprint = Some(fingerprint.trim_matches('\'').to_string());
+        } else if let Some(abi) = trimmed.strip_prefix("ABI: ") {
+            self.abi = abi.trim_matches('\'').to_string();
+        } else if let Some(cause) = trimmed.strip_prefix("Cause: ") {
+            self.cause = Some(cause.to_string());
+        } else if let Some(cmdline) = trimmed.strip_prefix("Cmdline: ") {
+            self.process = cmdline.to_string();
+        } else if trimmed.starts_with("pid: ") {
+            self.apply_process_line(trimmed);
+        } else if trimmed.starts_with("signal ") {
+            self.apply_signal_fields(trimmed);
+        }
+    }
+
+    // "pid: 1234, tid: 1250, name: RenderThread  >>> com.example.app <<<"
+    fn apply_process_line(&mut self, line: &str) {
+        let (ids, process) = line.split_once(">>>").unwrap_or((line, ""));
+        let process = process.trim().trim_end_matches("<<<").trim();
+        if !process.is_empty() {
+            self.process = process.to_string();
+        }
+        for part in ids.split(", ") {
+            if let Some(pid) = part.strip_prefix("pid: ") {
+                self.pid = pid.trim().parse().ok();
+            } else if let Some(tid) = part.strip_prefix("tid: ") {
+                self.tid = tid.trim().parse().ok();
+            } else if let Some(name) = part.strip_prefix("name: ") {
+                self.thread = name.trim().to_string();
+            }
+        }
+    }
+
+    // "signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0" from DEBUG, or libc's
+    // "Fatal signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0 in tid 1250 (RenderThread), pid 1234 (com.example.app)"
+    fn apply_signal_fields(&mut self, line: &str) {
+        for part in line.split(", ") {
+            // "in tid" follows the fault addr, or the code for signals without one (SIGABRT)
+            let (part, thread) = part.trim().split_once(" in tid ").unwrap_or((part.trim(), ""));
+            if !thread.is_empty() {
+                let (tid, name) = split_named_value(thread);
+                self.tid = tid.parse().ok();
+                self.thread = name.to_string();
+            }
+            if let Some(signal) = part.strip_prefix("Fatal signal ").or_else(|| part.strip_prefix("signal ")) {
+                let (number, name) = split_named_value(signal);
+                self.signal_number = number.parse().ok();
+                self.signal = name.to_string();
+            } else if let Some(code) = part.strip_prefix("code ") {
+                self.code = code.to_string();
+            } else if let Some(fault) = part.strip_prefix("fault addr ") {
+                self.fault_addr = Some(fault.trim().to_string());
+            } else if let Some(pid) = part.strip_prefix("pid ") {
+                let (pid, name) = split_named_value(pid);
+                self.pid = pid.parse().ok();
+                self.process = name.to_string();
+            }
+        }
+    }
+}
+
+// "11 (SIGSEGV)" -> ("11", "SIGSEGV")
+fn split_named_value(text: &str) -> (&str, &str) {
+    match text.split_once(" (") {
+        Some((value, name)) => (value.trim(), name.trim_end_matches(')')),
+        None => (text.trim(), ""),
+    }
+}
+
+// Collects the libc "Fatal signal" line and the DEBUG tombstone block that crash_dump
+// prints after it into a single NativeCrash.
+pub struct TombstoneDetector {
+    blocks: BlockGrouper<NativeCrash>,
+}
+
+impl TombstoneDetector {
+    pub fn new() -> Self {
+        Self { blocks: BlockGrouper::new(TOMBSTONE_IDLE) }
+    }
+
+    pub fn feed(&mut self, entry: &LogEntry) -> Vec<NativeCrash> {
+        let mut finished: Vec<NativeCrash> = self.blocks.end_after_gap(entry).into_iter().collect();
+        if entry.tag != DEBUG_TAG && entry.tag != LIBC_TAG {
+            return finished;
+        }
+
+        let mut extended = false;
+        for line in entry.message.lines() {
+            let trimmed = line.trim();
+            if entry.tag == LIBC_TAG {
+                if trimmed.starts_with("Fatal signal ") {
+                    let mut crash = NativeCrash::new(entry.timestamp);
+                    crash.apply_signal_fields(trimmed);
+                    finished.extend(self.blocks.start(crash, entry.pid));
+                    extended = true;
+                }
+                continue;
+            }
+
+            if trimmed.starts_with("*** *** ***") {
+                // A new tombstone header starts a new crash unless it belongs to the libc
+                // line before it, in which case crash_dump takes over logging it
+                if self.blocks.current().is_none_or(|c| c.has_debug_block) {
+                    finished.extend(self.blocks.start(NativeCrash::new(entry.timestamp), entry.pid));
+                }
+                self.blocks.hand_over(entry.pid);
+                if let Some(crash) = self.blocks.current_mut() {
+                    crash.has_debug_block = true;
+                }
+                extended = true;
+            } else if self.blocks.current().is_none() {
+                continue;
+            } else if !self.blocks.is_logger(entry.pid) {
+                // DEBUG output from another crash_dump, so this block is over
+                finished.extend(self.blocks.take());
+            } else if trimmed.starts_with("Tombstone written to") {
+                finished.extend(self.blocks.take());
+            } else if let Some(crash) = self.blocks.current_mut() {
+                crash.apply_debug_line(line);
+                extended = true;
+            }
+        }
+
+        if extended {
+            self.blocks.touch(entry);
+        }
+        finished
+    }
+
+    pub fn flush_stale(&mut self) -> Option<NativeCrash> {
+        self.blocks.flush_stale(Instant::now())
+    }
+
+    pub fn finish(&mut self) -> Option<NativeCrash> {
+        self.blocks.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as TimeDelta;
+
+    const CRASH_DUMP_PID: u32 = 5000;
+
+    const TOMBSTONE: &[&str] = &[
+        "*** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***",
+        "Build fingerprint: 'google/sdk_gphone64_arm64/emu64a:14/UE1A.230829.036/10:userdebug/dev-keys'",
+        "Revision: '0'",
+        "ABI: 'arm64'",
+        "Timestamp: 2024-03-21 10:23:45.678901234+0000",
+        "Cmdline: com.example.app",
+        "pid: 1234, tid: 1250, name: RenderThread  >>> com.example.app <<<",
+        "uid: 10123",
+        "signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0000000000000000",
+        "Cause: null pointer dereference",
+        "    x0  0000000000000000  x1  0000007fc0a1b2c8  x2  0000000000000010  x3  0000000000000000",
+        "",
+        "backtrace:",
+        "      #00 pc 0000000000001234  /data/app/~~abc==/com.example.app-xyz==/lib/arm64/libfoo.so (crash+8) (BuildId: 1a2b3c)",
+        "      #01 pc 0000000000005678  /apex/com.android.runtime/lib64/bionic/libc.so (__start_thread+64)",
+        "",
+        "Tombstone written to: /data/tombstones/tombstone_07",
+    ];
+
+    fn entry(pid: u32, tag: &str, line: &str) -> LogEntry {
+        let mut entry = LogEntry::new(Local::now(), 'F', tag, line);
+        entry.pid = Some(pid);
+        entry
+    }
+
+    fn feed(detector: &mut TombstoneDetector, lines: &[&str]) -> Vec<NativeCrash> {
+        lines.iter()
+            .flat_map(|line| detector.feed(&entry(CRASH_DUMP_PID, DEBUG_TAG, line)))
+            .collect()
+    }
+
+    fn frame(line: &str) -> NativeFrame {
+        NativeFrame::parse(line).unwrap_or_else(|| panic!("not a frame: {:?}", line))
+    }
+
+    #[test]
+    fn parse_frame_fields() {
+        let frame = frame("      #03 pc 00000000000a1b2c  /data/app/~~x==/lib/arm64/libnative.so (crash_here+20) (BuildId: 1a2b3c)");
+        assert_eq!((frame.index, frame.offset), (3, 0xa1b2c));
+        assert_eq!(frame.module, "/data/app/~~x==/lib/arm64/libnative.so");
+        assert_eq!((frame.symbol.as_deref(), frame.symbol_offset), (Some("crash_here"), Some(20)));
+        assert_eq!(frame.build_id.as_deref(), Some("1a2b3c"));
+        assert_eq!((frame.apk_offset, frame.file, frame.line), (None, None, None));
+    }
+
+    #[test]
+    fn parse_frame_variants() {
+        let bare = frame("#00 pc 0000000000001105  /system/lib64/libc.so");
+        assert_eq!((bare.symbol, bare.symbol_offset), (None, None));
+
+        let apk_line = "#01 pc 0000000000042000  /data/app/base.apk!libfoo.so (offset 0x1000) (foo::bar(int)+8)";
+        let apk = frame(apk_line);
+        assert_eq!((apk.module.as_str(), apk.apk_offset), ("/data/app/base.apk!libfoo.so", Some(0x1000)));
+        assert_eq!((apk.symbol.as_deref(), apk.symbol_offset), (Some("foo::bar(int)"), Some(8)));
+        assert_eq!(apk.to_string(), apk_line);
+
+        let symbolicated = frame("#02 pc 0000000000001105  /data/libfoo.so (foo::crash(int*)+12) [/src/foo.cpp:3]");
+        assert_eq!((symbolicated.file.as_deref(), symbolicated.line), (Some("/src/foo.cpp"), Some(3)));
+        assert_eq!(symbolicated.to_string(), "#02 pc 0000000000001105  /data/libfoo.so (foo::crash(int*)+12) [/src/foo.cpp:3]");
+
+        assert!(NativeFrame::parse("backtrace:").is_none());
+        assert!(NativeFrame::parse("#00 pc zzzz  /system/lib64/libc.so").is_none());
+    }
+
+    #[test]
+    fn full_tombstone() {
+        let mut detector = TombstoneDetector::new();
+        let finished = feed(&mut detector, TOMBSTONE);
+
+        assert_eq!(finished.len(), 1);
+        let crash = &finished[0];
+        assert_eq!(crash.process, "com.example.app");
+        assert_eq!((crash.pid, crash.tid, crash.thread.as_str()), (Some(1234), Some(1250), "RenderThread"));
+        assert_eq!((crash.signal_number, crash.signal.as_str()), (Some(11), "SIGSEGV"));
+        assert_eq!(crash.code, "1 (SEGV_MAPERR)");
+        assert_eq!(crash.fault_addr.as_deref(), Some("0x0000000000000000"));
+        assert_eq!(crash.abi, "arm64");
+        assert_eq!(crash.cause.as_deref(), Some("null pointer dereference"));
+        assert!(crash.fingerprint.as_deref().is_some_and(|f| f.starts_with("google/sdk_gphone64_arm64")));
+        assert_eq!(crash.frames.len(), 2);
+        assert_eq!(crash.frames[0].build_id.as_deref(), Some("1a2b3c"));
+        assert!(detector.finish().is_none());
+    }
+
+    #[test]
+    fn libc_line_merges_with_the_debug_header() {
+        let mut detector = TombstoneDetector::new();
+        let fatal = "Fatal signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0 in tid 1250 (RenderThread), pid 1234 (com.example.app)";
+        assert!(detector.feed(&entry(1234, LIBC_TAG, fatal)).is_empty());
+        let finished = feed(&mut detector, TOMBSTONE);
+
+        assert_eq!(finished.len(), 1);
+        let crash = &finished[0];
+        assert_eq!((crash.pid, crash.tid), (Some(1234), Some(1250)));
+        assert_eq!(crash.frames.len(), 2);
+    }
+
+    #[test]
+    fn libc_line_alone() {
+        let mut detector = TombstoneDetector::new();
+        detector.feed(&entry(1234, LIBC_TAG, "Fatal signal 6 (SIGABRT), code -1 (SI_QUEUE) in tid 1234 (main), pid 1234 (com.example.app)"));
+        let crash = detector.finish().unwrap();
+        assert_eq!((crash.signal.as_str(), crash.process.as_str(), crash.thread.as_str()), ("SIGABRT", "com.example.app", "main"));
+        assert!(crash.frames.is_empty());
+    }
+
+    #[test]
+    fn tombstone_written_closes_the_block() {
+        let mut detector = TombstoneDetector::new();
+        let finished = feed(&mut detector, TOMBSTONE);
+        assert_eq!(finished.len(), 1);
+        // Stray DEBUG output afterwards doesn't open or extend anything
+        assert!(feed(&mut detector, &["      #02 pc 0000000000009999  /system/lib64/libart.so"]).is_empty());
+        assert!(detector.finish().is_none());
+    }
+
+    #[test]
+    fn other_threads_are_ignored() {
+        let mut detector = TombstoneDetector::new();
+        let finished = feed(&mut detector, &[
+            "*** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***",
+            "pid: 1234, tid: 1250, name: RenderThread  >>> com.example.app <<<",
+            "signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0",
+            "backtrace:",
+            "      #00 pc 0000000000001234  /data/app/lib/arm64/libfoo.so (crash+8)",
+            "      #01 pc 0000000000005678  /apex/com.android.runtime/lib64/bionic/libc.so (__start_thread+64)",
+            "--- --- --- --- --- --- --- --- --- --- --- --- --- --- --- ---",
+            "pid: 1234, tid: 1234, name: example.app  >>> com.example.app <<<",
+            "backtrace:",
+            "      #00 pc 000000000009abcd  /apex/com.android.runtime/lib64/bionic/libc.so (__epoll_pwait+8)",
+            "Tombstone written to: /data/tombstones/tombstone_07",
+        ]);
+
+        assert_eq!(finished.len(), 1);
+        let crash = &finished[0];
+        assert_eq!((crash.pid, crash.tid, crash.thread.as_str()), (Some(1234), Some(1250), "RenderThread"));
+        assert_eq!(crash.signal, "SIGSEGV");
+        let symbols: Vec<&str> = crash.frames.iter().filter_map(|f| f.symbol.as_deref()).collect();
+        assert_eq!(symbols, ["crash", "__start_thread"]);
+    }
+
+    #[test]
+    fn interleaved_pids_end_the_block() {
+        let mut detector = TombstoneDetector::new();
+        feed(&mut detector, &TOMBSTONE[..14]);
+        let finished = detector.feed(&entry(6000, DEBUG_TAG, "pid: 777, tid: 777, name: other  >>> com.other <<<"));
+
+        assert_eq!(finished.len(), 1);
+        let crash = &finished[0];
+        assert_eq!((crash.process.as_str(), crash.pid), ("com.example.app", Some(1234)));
+        assert_eq!(crash.frames.len(), 1);
+        // The rest of the first dump has nothing left to attach to
+        assert!(feed(&mut detector, &TOMBSTONE[14..]).is_empty());
+        assert!(detector.finish().is_none());
+    }
+
+    #[test]
+    fn device_time_gap_ends_the_block() {
+        let now = Local::now();
+        let mut detector = TombstoneDetector::new();
+        let mut header = entry(CRASH_DUMP_PID, DEBUG_TAG, TOMBSTONE[0]);
+        header.timestamp = now;
+        detector.feed(&header);
+
+        let mut later = entry(42, "Other", "busy");
+        later.timestamp = now + TimeDelta::seconds(1);
+        assert!(detector.feed(&later).is_empty());
+        later.timestamp = now + TimeDelta::seconds(10);
+        assert_eq!(detector.feed(&later).len(), 1);
+    }
+
+    #[test]
+    fn unrelated_debug_output_does_not_keep_the_block_alive() {
+        let mut detector = TombstoneDetector::new();
+        feed(&mut detector, &TOMBSTONE[..7]);
+        let finished = detector.feed(&entry(6000, DEBUG_TAG, "unrelated"));
+        assert_eq!(finished.len(), 1);
+        assert!(detector.blocks.flush_stale(Instant::now() + TOMBSTONE_IDLE).is_none());
+    }
+}
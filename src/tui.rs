@@ -4,6 +4,7 @@ use ratatui::{
     widgets::{Block, Borders, List, ListItem, Paragraph, Tabs},
     layout::{Layout, Direction, Constraint, Rect},
     style::{Color, Style, Modifier},
+    text::{Line, Span},
     Terminal, Frame,
 };
 use crossterm::{
@@ -11,36 +12,161 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use crate::storage::StorageUpdate;
+use crate::crash::{CrashMonitor, CrashRecord};
+use crate::events::{EventField, EventValue};
+use crate::source::LogSource;
+use crate::replay::{Replay, SEEK_STEP};
+use crate::ingest::{IngestHealth, IngestReceiver};
+use crate::query::Query;
+use crate::filterspec::FilterSpecs;
+use crate::context::{ContextRow, ContextWindow};
+use regex::{Regex, RegexBuilder};
+use std::sync::Arc;
 use copypasta::{ClipboardContext, ClipboardProvider};
 use std::time::{Instant, Duration};
-use std::process::Command;
 use colored::Colorize;
+use chrono::{DateTime, Local};
+use serde::{Serialize, Deserialize};
 
 #[cfg(feature = "macos")]
 use mac_notification_sys::{get_bundle_identifier_or_default, send_notification, Notification};
 
+// Timestamp layout used when displaying or copying entries, matching logcat's threadtime
+pub const TIMESTAMP_FORMAT: &str = "%m-%d %H:%M:%S%.3f";
+
+// Search text starting with this is a regex regardless of the Tab toggle
+const REGEX_PREFIX: &str = "re:";
+
+// Rows kept above a match jumped to with n/N, so what led up to it stays visible
+const FIND_CONTEXT: usize = 5;
+
+#[derive(Clone)]
 pub struct LogEntry {
+    pub timestamp: DateTime<Local>,
+    pub uid: Option<String>,  // Only present with logcat's uid modifier
+    pub pid: Option<u32>,
+    pub tid: Option<u32>,
+    pub priority: char,  // Raw logcat priority character (V/D/I/W/E/...)
     pub level: LogLevel,
-    pub timestamp: String,
     pub tag: String,
     pub message: String,
+    pub buffer: Option<String>,  // Set once a "--------- beginning of <buffer>" divider has been seen
+    pub fields: Vec<EventField>,  // Decoded events-buffer payload
+    pub device: Option<String>,  // Serial of the device the entry came from, when known
 }
 
-#[derive(Clone, Copy, PartialEq)]
+impl LogEntry {
+    pub fn new(timestamp: DateTime<Local>, priority: char, tag: &str, message: &str) -> Self {
+        Self {
+            timestamp,
+            uid: None,
+            pid: None,
+            tid: None,
+            priority,
+            level: LogLevel::from_priority(priority),
+            tag: tag.to_string(),
+            message: message.to_string(),
+            buffer: None,
+            fields: Vec::new(),
+            device: None,
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&EventValue> {
+        self.fields
+            .iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+            .map(|field| &field.value)
+    }
+
+    // Render the entry back into a threadtime line, e.g. for pasting into other tools
+    pub fn to_logcat_line(&self) -> String {
+        format!(
+            "{} {:>5} {:>5} {} {:<8}: {}",
+            self.timestamp.format(TIMESTAMP_FORMAT),
+            self.pid.map(|p| p.to_string()).unwrap_or_default(),
+            self.tid.map(|t| t.to_string()).unwrap_or_default(),
+            self.priority,
+            self.tag,
+            self.message
+        )
+    }
+}
+
+// Declared in Android priority order, so `level >= LogLevel::Warning` means "W and above"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum LogLevel {
-    Error,
-    Warning,
-    Info,
-    Debug,
-    Verbose,
+    #[serde(rename = "UNKNOWN")]
     Unknown,
+    #[serde(rename = "VERBOSE")]
+    Verbose,
+    #[serde(rename = "DEBUG")]
+    Debug,
+    #[serde(rename = "INFO")]
+    Info,
+    #[serde(rename = "WARN")]
+    Warning,
+    #[serde(rename = "ERROR")]
+    Error,
+    #[serde(rename = "FATAL")]
+    Fatal,
+    #[serde(rename = "ASSERT")]
+    Assert,
+    #[serde(rename = "SILENT")]
+    Silent,
 }
 
 impl LogLevel {
+    pub fn from_priority(priority: char) -> Self {
+        match priority {
+            'S' => LogLevel::Silent,
+            'A' => LogLevel::Assert,
+            'F' => LogLevel::Fatal,
+            'E' => LogLevel::Error,
+            'W' => LogLevel::Warning,
+            'I' => LogLevel::Info,
+            'D' => LogLevel::Debug,
+            'V' => LogLevel::Verbose,
+            _ => LogLevel::Unknown,
+        }
+    }
+
+    // A level typed by the user: a priority letter ("W") or a name ("warn", "error")
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_uppercase();
+        let level = match name.as_str() {
+            "WARNING" => LogLevel::Warning,
+            _ if name.chars().count() == 1 => LogLevel::from_priority(name.chars().next()?),
+            _ => [
+                LogLevel::Silent, LogLevel::Assert, LogLevel::Fatal, LogLevel::Error,
+                LogLevel::Warning, LogLevel::Info, LogLevel::Debug, LogLevel::Verbose,
+            ].into_iter().find(|level| level.as_str() == name)?,
+        };
+        (level != LogLevel::Unknown).then_some(level)
+    }
+
+    // Inverse of from_priority; '?' for entries that had no logcat header
+    pub fn priority(&self) -> char {
+        match self {
+            LogLevel::Silent => 'S',
+            LogLevel::Assert => 'A',
+            LogLevel::Fatal => 'F',
+            LogLevel::Error => 'E',
+            LogLevel::Warning => 'W',
+            LogLevel::Info => 'I',
+            LogLevel::Debug => 'D',
+            LogLevel::Verbose => 'V',
+            LogLevel::Unknown => '?',
+        }
+    }
+
     fn color(&self) -> Color {
         match self {
+            LogLevel::Silent => Color::DarkGray,
+            LogLevel::Assert => Color::LightMagenta,
+            LogLevel::Fatal => Color::Magenta,
             LogLevel::Error => Color::Red,
             LogLevel::Warning => Color::Yellow,
             LogLevel::Info => Color::Green,
@@ -52,6 +178,9 @@ impl LogLevel {
 
     pub fn as_str(&self) -> &'static str {
         match self {
+            LogLevel::Silent => "SILENT",
+            LogLevel::Assert => "ASSERT",
+            LogLevel::Fatal => "FATAL",
             LogLevel::Error => "ERROR",
             LogLevel::Warning => "WARN",
             LogLevel::Info => "INFO",
@@ -60,6 +189,20 @@ impl LogLevel {
             LogLevel::Unknown => "UNKNOWN",
         }
     }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            LogLevel::Silent => "🔇",
+            LogLevel::Assert => "🚨",
+            LogLevel::Fatal => "💀",
+            LogLevel::Error => "🔴",
+            LogLevel::Warning => "⚠️",
+            LogLevel::Info => "ℹ️",
+            LogLevel::Debug => "🔧",
+            LogLevel::Verbose => "📝",
+            LogLevel::Unknown => "❓",
+        }
+    }
 }
 
 // Update View enum
@@ -68,6 +211,7 @@ pub enum View {
     Logs,
     Stats,
     Storage,
+    Crashes,
 }
 
 // Add Display implementation for View
@@ -77,6 +221,7 @@ impl std::fmt::Display for View {
             View::Logs => write!(f, "Logs"),
             View::Stats => write!(f, "Stats"),
             View::Storage => write!(f, "Storage"),
+            View::Crashes => write!(f, "Crashes"),
         }
     }
 }
@@ -90,14 +235,36 @@ pub struct AppState {
     pub paused: bool,
     pub search_query: String,
     pub search_mode: bool,
+    pub search_regex: bool,  // Tab switches the search between literal text and regex
+    pub search_pattern: Option<Regex>,  // The search compiled, literal text escaped
+    pub search_error: Option<String>,  // Why the search text isn't a valid regex
+    pub search_filter: bool,  // Hide rows that don't match the search, instead of just finding them
+    pub find_matches: Vec<usize>,  // Positions in filtered_logs that match the search
+    pub find_cursor: Option<usize>,  // Position in filtered_logs of the match jumped to
+    pub query: Option<Query>,  // Set with --query or the :filter prompt
+    pub filterspecs: Option<FilterSpecs>,  // Set with positional filterspecs or :spec
+    pub context_before: usize,  // Rows shown around each row that passes the filters
+    pub context_after: usize,
+    pub context_rows: HashSet<usize>,  // Indices into logs shown only as context
+    pub separators: HashSet<usize>,  // Indices into logs that start a new context group
+    pub command_mode: bool,  // Typing a ':' command
+    pub command: String,
+    pub command_error: Option<String>,  // Why the last command was rejected
     pub storage_info: Option<StorageInfo>,
     pub stats: LogStats,
     pub level_filters: Vec<LogLevel>,  // Enabled log levels
     pub tail_mode: bool,  // Add this field
     pub status_message: Option<(String, Instant)>,  // (message, timestamp)
-    pub connection_status: ConnectionStatus,
+    pub connection_status: Option<ConnectionStatus>,  // None for sources without a device
     pub notify_on_error: bool,
+    #[cfg_attr(not(feature = "macos"), allow(dead_code))]
     pub last_notification: Option<Instant>,
+    pub crashes: Vec<CrashRecord>,
+    pub selected_crash: usize,
+    crash_monitor: CrashMonitor,
+    pub devices: Vec<String>,  // Serials seen so far, in order of appearance
+    pub device_filter: Option<String>,  // Only show this device's entries
+    pub ingest: Option<IngestHealth>,  // None while replaying
 }
 
 pub struct StorageInfo {
@@ -106,12 +273,16 @@ pub struct StorageInfo {
     pub file_count: usize,
 }
 
+#[derive(Default)]
 pub struct LogStats {
+    pub assert_count: usize,
+    pub fatal_count: usize,
     pub error_count: usize,
     pub warning_count: usize,
     pub info_count: usize,
     pub debug_count: usize,
     pub verbose_count: usize,
+    pub anr_count: usize,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -131,34 +302,101 @@ impl AppState {
             paused: false,
             search_query: String::new(),
             search_mode: false,
+            search_regex: false,
+            search_pattern: None,
+            search_error: None,
+            search_filter: false,
+            find_matches: Vec::new(),
+            find_cursor: None,
+            query: None,
+            filterspecs: None,
+            context_before: 0,
+            context_after: 0,
+            context_rows: HashSet::new(),
+            separators: HashSet::new(),
+            command_mode: false,
+            command: String::new(),
+            command_error: None,
             storage_info: None,
             stats: LogStats {
+                assert_count: 0,
+                fatal_count: 0,
                 error_count: 0,
                 warning_count: 0,
                 info_count: 0,
                 debug_count: 0,
                 verbose_count: 0,
+                anr_count: 0,
             },
             level_filters: vec![  // Start with all levels enabled
+                LogLevel::Assert,
+                LogLevel::Fatal,
                 LogLevel::Error,
                 LogLevel::Warning,
                 LogLevel::Info,
                 LogLevel::Debug,
                 LogLevel::Verbose,
+                LogLevel::Unknown,  // Lines without a logcat header: raw format, dividers, pasted text
             ],
             tail_mode: true,  // Start with tail mode enabled
             status_message: None,
-            connection_status: ConnectionStatus::Connected,
+            connection_status: None,
             notify_on_error: true,
             last_notification: None,
+            crashes: Vec::new(),
+            selected_crash: 0,
+            crash_monitor: CrashMonitor::new(),
+            devices: Vec::new(),
+            device_filter: None,
+            ingest: None,
+        }
+    }
+
+    // Forget everything shown so far, e.g. when a replay seeks backwards
+    pub fn reset(&mut self) {
+        self.logs.clear();
+        self.filtered_logs.clear();
+        self.context_rows.clear();
+        self.separators.clear();
+        self.find_matches.clear();
+        self.find_cursor = None;
+        self.scroll = 0;
+        self.stats = LogStats::default();
+        self.crashes.clear();
+        self.selected_crash = 0;
+        self.crash_monitor = CrashMonitor::new();
+    }
+
+    // Add a batch of entries, refiltering once at the end rather than per entry.
+    // Returns whether any were kept.
+    pub fn add_logs(&mut self, entries: impl IntoIterator<Item = LogEntry>) -> bool {
+        let mut added = false;
+        for entry in entries {
+            added |= self.add_log(entry);
+        }
+        if added {
+            self.update_filtered_logs();
         }
+        added
     }
 
-    pub fn add_log(&mut self, entry: LogEntry) {
+    // Returns whether the entry was kept; the caller refilters once it has added a batch
+    fn add_log(&mut self, entry: LogEntry) -> bool {
+        // Crash detection keeps running while paused so no crash is missed
+        for record in self.crash_monitor.feed(&entry) {
+            self.record_crash(record);
+        }
+
+        if let Some(device) = &entry.device {
+            if !self.devices.contains(device) {
+                self.devices.push(device.clone());
+            }
+        }
+
         if !self.paused {
             // Send macOS notification for errors
             #[cfg(feature = "macos")]
-            if self.notify_on_error && entry.level == LogLevel::Error {
+            if self.notify_on_error && entry.level >= LogLevel::Error {
                 // Limit notifications to once every 5 seconds
                 if self.last_notification.map_or(true, |t| t.elapsed() > Duration::from_secs(5)) {
                     let bundle = get_bundle_identifier_or_default("com.devinsight.app");
@@ -188,19 +426,35 @@ impl AppState {
             
             // Update statistics
             match entry.level {
+                LogLevel::Assert => self.stats.assert_count += 1,
+                LogLevel::Fatal => self.stats.fatal_count += 1,
                 LogLevel::Error => self.stats.error_count += 1,
                 LogLevel::Warning => self.stats.warning_count += 1,
                 LogLevel::Info => self.stats.info_count += 1,
                 LogLevel::Debug => self.stats.debug_count += 1,
                 LogLevel::Verbose => self.stats.verbose_count += 1,
-                LogLevel::Unknown => (),
+                LogLevel::Silent | LogLevel::Unknown => (),
             }
 
             self.logs.push_back(entry);
-            self.update_filtered_logs();
+            return true;
+        }
+        false
+    }
+
+    pub fn flush_stale_crashes(&mut self) {
+        for record in self.crash_monitor.flush_stale() {
+            self.record_crash(record);
         }
     }
 
+    fn record_crash(&mut self, record: CrashRecord) {
+        if let CrashRecord::Anr(_) = record {
+            self.stats.anr_count += 1;
+        }
+        self.crashes.push(record);
+    }
+
     pub fn toggle_level(&mut self, level: LogLevel) {
         if let Some(pos) = self.level_filters.iter().position(|&l| l == level) {
             self.level_filters.remove(pos);
@@ -210,25 +464,215 @@ impl AppState {
         self.update_filtered_logs();
     }
 
+    // Run a ':' command: `filter <expression>` or `spec <filterspecs>`. Either one alone
+    // clears it.
+    pub fn run_command(&mut self) -> Result<String, String> {
+        let command = self.command.trim();
+        let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+        match name {
+            "filter" if argument.trim().is_empty() => {
+                self.query = None;
+                self.update_filtered_logs();
+                Ok("Filter cleared".to_string())
+            }
+            "filter" => {
+                let query = Query::parse(argument.trim()).map_err(|e| e.to_string())?;
+                let message = format!("Filter: {}", query.as_str());
+                self.query = Some(query);
+                self.update_filtered_logs();
+                Ok(message)
+            }
+            "spec" if argument.trim().is_empty() => {
+                self.filterspecs = None;
+                self.update_filtered_logs();
+                Ok("Filterspecs cleared".to_string())
+            }
+            "spec" => {
+                let specs = FilterSpecs::parse(&[argument]).map_err(|e| e.to_string())?;
+                let message = format!("Filterspecs: {}", specs.as_str());
+                self.filterspecs = Some(specs);
+                self.update_filtered_logs();
+                Ok(message)
+            }
+            _ => Err(format!("Unknown command '{}' (try :filter <expression> or :spec <Tag:Level ...>)", name)),
+        }
+    }
+
+    // Whether the search is a regex, by the /re: prefix or the Tab toggle, and its text
+    fn search_text(&self) -> (bool, &str) {
+        match self.search_query.strip_prefix(REGEX_PREFIX) {
+            Some(pattern) => (true, pattern),
+            None => (self.search_regex, &self.search_query),
+        }
+    }
+
+    // Recompile the search after it changes. Both modes ignore case. An invalid regex
+    // keeps the previous pattern so the view doesn't jump while it's being typed.
+    pub fn update_search(&mut self) {
+        let (is_regex, text) = self.search_text();
+        if text.is_empty() {
+            self.search_pattern = None;
+            self.search_error = None;
+        } else {
+            let pattern = if is_regex { text.to_string() } else { regex::escape(text) };
+            match RegexBuilder::new(&pattern).case_insensitive(true).build() {
+                Ok(pattern) => {
+                    self.search_pattern = Some(pattern);
+                    self.search_error = None;
+                }
+                Err(e) => self.search_error = Some(format!("Invalid regex: {}", crate::query::regex_problem(&e))),
+            }
+        }
+        self.find_cursor = None;
+        self.update_filtered_logs();
+    }
+
+    // Switch between hiding non-matching rows and only finding matches, keeping the
+    // current match under the cursor
+    pub fn toggle_search_filter(&mut self) {
+        self.search_filter = !self.search_filter;
+        self.refilter_keeping_cursor();
+    }
+
+    // Grow or shrink the context shown around each row by one on both sides
+    pub fn adjust_context(&mut self, grow: bool) {
+        if grow {
+            self.context_before += 1;
+            self.context_after += 1;
+        } else {
+            self.context_before = self.context_before.saturating_sub(1);
+            self.context_after = self.context_after.saturating_sub(1);
+        }
+        self.refilter_keeping_cursor();
+    }
+
+    // Rebuild filtered_logs, following the current match to its new position
+    fn refilter_keeping_cursor(&mut self) {
+        let current = self.find_cursor.and_then(|position| self.filtered_logs.get(position).copied());
+        self.update_filtered_logs();
+        if let Some(position) = current.and_then(|index| self.filtered_logs.iter().position(|&i| i == index)) {
+            self.move_find_cursor(position);
+        }
+    }
+
+    // Jump to the next (or previous) match, wrapping around. The first jump starts from
+    // the top row, or from the newest row in tail mode.
+    pub fn find_next(&mut self, forward: bool) {
+        let (Some(&first), Some(&last)) = (self.find_matches.first(), self.find_matches.last()) else {
+            self.status_message = Some(("No matches".to_string(), Instant::now()));
+            return;
+        };
+        let next = match (self.find_cursor, forward) {
+            (Some(cursor), true) => self.find_matches.iter().find(|&&p| p > cursor).copied().unwrap_or(first),
+            (Some(cursor), false) => self.find_matches.iter().rev().find(|&&p| p < cursor).copied().unwrap_or(last),
+            (None, _) if self.tail_mode => last,
+            (None, true) => self.find_matches.iter().find(|&&p| p >= self.scroll).copied().unwrap_or(first),
+            (None, false) => self.find_matches.iter().rev().find(|&&p| p <= self.scroll).copied().unwrap_or(last),
+        };
+        self.move_find_cursor(next);
+    }
+
+    fn move_find_cursor(&mut self, position: usize) {
+        self.find_cursor = Some(position);
+        self.tail_mode = false;
+        self.scroll = position.saturating_sub(FIND_CONTEXT);
+    }
+
+    // "match 3/17" once a match has been jumped to, otherwise "17 matches"
+    pub fn find_status(&self) -> Option<String> {
+        self.search_pattern.as_ref()?;
+        let total = self.find_matches.len();
+        Some(match self.find_cursor.and_then(|cursor| self.find_matches.binary_search(&cursor).ok()) {
+            Some(index) => format!("match {}/{}", index + 1, total),
+            None => format!("{} matches", total),
+        })
+    }
+
+    // The row y/c copy: the current match, or else the top row
+    pub fn current_row(&self) -> usize {
+        self.find_cursor.unwrap_or(self.scroll)
+    }
+
+    fn matches_search(&self, log: &LogEntry) -> bool {
+        let Some(pattern) = &self.search_pattern else {
+            return true;
+        };
+        // "name=value" matches an event field exactly when the entry has that field
+        let (is_regex, search_term) = self.search_text();
+        let field_match = search_term.split_once('=')
+            .filter(|_| !is_regex)
+            .and_then(|(name, value)| {
+                log.field(name.trim()).map(|field| field.to_string().eq_ignore_ascii_case(value.trim()))
+            });
+        field_match.unwrap_or_else(|| {
+            pattern.is_match(&log.message) ||
+            pattern.is_match(&log.tag) ||
+            pattern.is_match(log.level.as_str())
+        })
+    }
+
+    // Step through all devices -> first device -> ... -> last device -> all devices
+    pub fn cycle_device_filter(&mut self) {
+        let next = match &self.device_filter {
+            None => 0,
+            Some(current) => self.devices.iter().position(|d| d == current).map_or(0, |i| i + 1),
+        };
+        self.device_filter = self.devices.get(next).cloned();
+        self.update_filtered_logs();
+    }
+
     fn update_filtered_logs(&mut self) {
-        self.filtered_logs = self.logs
+        let mut filtered_logs = Vec::new();
+        let mut context_rows = HashSet::new();
+        let mut separators = HashSet::new();
+        let mut window = ContextWindow::new(self.context_before, self.context_after);
+        let mut separated = false;
+
+        // Rows from other devices aren't shown even as context
+        let device_logs = self.logs
             .iter()
             .enumerate()
-            .filter(|(_, log)| {
-                let level_match = self.level_filters.contains(&log.level);
-                let search_match = if self.search_query.is_empty() {
-                    true
-                } else {
-                    let search_term = self.search_query.to_lowercase();
-                    log.message.to_lowercase().contains(&search_term) ||
-                    log.tag.to_lowercase().contains(&search_term) ||
-                    log.level.as_str().to_lowercase().contains(&search_term)
+            .filter(|(_, log)| self.device_filter.as_ref().is_none_or(|device| log.device.as_ref() == Some(device)));
+        for (i, log) in device_logs {
+            let level_match = self.level_filters.contains(&log.level);
+            let search_match = !self.search_filter || self.matches_search(log);
+            let query_match = self.query.as_ref().is_none_or(|query| query.matches(log));
+            let spec_match = self.filterspecs.as_ref().is_none_or(|specs| specs.matches(log));
+
+            for row in window.push(i, level_match && search_match && query_match && spec_match) {
+                let index = match row {
+                    ContextRow::Separator => {
+                        separated = true;
+                        continue;
+                    }
+                    ContextRow::Context(index) => {
+                        context_rows.insert(index);
+                        index
+                    }
+                    ContextRow::Match(index) => index,
                 };
-                
-                level_match && search_match
-            })
-            .map(|(i, _)| i)
-            .collect();
+                if std::mem::take(&mut separated) {
+                    separators.insert(index);
+                }
+                filtered_logs.push(index);
+            }
+        }
+        self.filtered_logs = filtered_logs;
+        self.context_rows = context_rows;
+        self.separators = separators;
+
+        self.find_matches = match self.search_pattern {
+            Some(_) => self.filtered_logs
+                .iter()
+                .enumerate()
+                .filter(|(_, &index)| self.matches_search(&self.logs[index]))
+                .map(|(position, _)| position)
+                .collect(),
+            None => Vec::new(),
+        };
+        if self.find_cursor.is_some_and(|cursor| cursor >= self.filtered_logs.len()) {
+            self.find_cursor = None;
+        }
 
         // Update scroll position if in tail mode
         if self.tail_mode {
@@ -240,14 +684,17 @@ impl AppState {
 pub struct Tui {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     state: AppState,
-    log_rx: std::sync::mpsc::Receiver<LogEntry>,
+    source: Arc<dyn LogSource>,
+    replay: Option<Replay>,
+    log_rx: IngestReceiver,
     storage_rx: std::sync::mpsc::Receiver<StorageUpdate>,
     clipboard: Option<ClipboardContext>,
 }
 
 impl Tui {
     pub fn new(
-        log_rx: std::sync::mpsc::Receiver<LogEntry>,
+        source: Arc<dyn LogSource>,
+        log_rx: IngestReceiver,
         storage_rx: std::sync::mpsc::Receiver<StorageUpdate>,
     ) -> io::Result<Self> {
         enable_raw_mode()?;
@@ -257,16 +704,40 @@ impl Tui {
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
         let clipboard = ClipboardContext::new().ok();
+        let mut state = AppState::new();
+        state.connection_status = source.connection_status();
 
         Ok(Self {
             terminal,
-            state: AppState::new(),
+            state,
+            source,
+            replay: None,
             log_rx,
             storage_rx,
             clipboard,
         })
     }
 
+    // Start with the level, query (including --tag) and filterspecs given on the command line
+    pub fn set_filters(&mut self, level: Option<LogLevel>, query: Option<Query>, filterspecs: Option<FilterSpecs>) {
+        self.state.query = query;
+        self.state.filterspecs = filterspecs;
+        if let Some(level) = level {
+            self.state.level_filters = vec![level];
+        }
+    }
+
+    // Rows shown around each row that passes the filters, from -A/-B/-C
+    pub fn set_context(&mut self, before: usize, after: usize) {
+        self.state.context_before = before;
+        self.state.context_after = after;
+    }
+
+    // Play a recorded session instead of waiting on the log channel
+    pub fn start_replay(&mut self, replay: Replay) {
+        self.replay = Some(replay);
+    }
+
     pub fn run(&mut self) -> io::Result<()> {
         const SPINNERS: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
         let mut spinner_idx = 0;
@@ -278,7 +749,7 @@ impl Tui {
         let mut last_check = Instant::now();
         const CHECK_INTERVAL: Duration = Duration::from_secs(5);
 
-        while collected < INITIAL_BATCH_SIZE && no_logs_count < MAX_WAIT_CYCLES {
+        while self.replay.is_none() && collected < INITIAL_BATCH_SIZE && no_logs_count < MAX_WAIT_CYCLES {
             self.terminal.draw(|f| {
                 let area = f.size();
                 let loading_area = Rect::new(
@@ -306,7 +777,7 @@ impl Tui {
 
             spinner_idx = (spinner_idx + 1) % SPINNERS.len();
             
-            if let Ok(log) = self.log_rx.try_recv() {
+            if let Some(log) = self.log_rx.try_recv() {
                 self.state.add_log(log);
                 collected += 1;
                 no_logs_count = 0;
@@ -316,18 +787,8 @@ impl Tui {
             }
 
             if last_check.elapsed() >= CHECK_INTERVAL {
-                // Check ADB connection
-                match Command::new("adb").args(["get-state"]).output() {
-                    Ok(output) if output.status.success() => {
-                        self.state.connection_status = ConnectionStatus::Connected;
-                    }
-                    Ok(_) => {
-                        self.state.connection_status = ConnectionStatus::Disconnected;
-                    }
-                    Err(_e) => {
-                        self.state.connection_status = ConnectionStatus::Error;
-                    }
-                }
+                // Check the device connection, for sources that have one
+                self.state.connection_status = self.source.connection_status();
                 last_check = Instant::now();
             }
         }
@@ -339,11 +800,20 @@ impl Tui {
         // Main event loop
         loop {
             // Process any new logs
-            while let Ok(log) = self.log_rx.try_recv() {
-                self.state.add_log(log);
-                if self.state.tail_mode {
-                    self.state.scroll = self.state.filtered_logs.len().saturating_sub(1);
-                }
+            let replayed = self.replay.as_mut().map(Replay::tick).unwrap_or_default();
+            let added = self.state.add_logs(replayed.into_iter().chain(self.log_rx.drain()));
+            if added && self.state.tail_mode {
+                self.state.scroll = self.state.filtered_logs.len().saturating_sub(1);
+            }
+            if self.replay.is_none() {
+                self.state.ingest = Some(self.log_rx.health());
+            }
+
+            self.state.flush_stale_crashes();
+
+            if last_check.elapsed() >= CHECK_INTERVAL {
+                self.state.connection_status = self.source.connection_status();
+                last_check = Instant::now();
             }
 
             // Process storage updates
@@ -360,25 +830,55 @@ impl Tui {
             if event::poll(std::time::Duration::from_millis(50))? {
                 match event::read()? {
                     Event::Key(key) => {
-                        if self.state.search_mode {
+                        if self.state.command_mode {
+                            match key.code {
+                                KeyCode::Esc => {
+                                    self.state.command_mode = false;
+                                    self.state.command_error = None;
+                                }
+                                KeyCode::Enter => match self.state.run_command() {
+                                    Ok(message) => {
+                                        self.state.command_mode = false;
+                                        self.state.command_error = None;
+                                        self.state.status_message = Some((message, Instant::now()));
+                                    }
+                                    // Keep the prompt open so the expression can be fixed
+                                    Err(error) => self.state.command_error = Some(error),
+                                },
+                                KeyCode::Char(c) => {
+                                    self.state.command.push(c);
+                                    self.state.command_error = None;
+                                }
+                                KeyCode::Backspace => {
+                                    self.state.command.pop();
+                                    self.state.command_error = None;
+                                }
+                                _ => {}
+                            }
+                        } else if self.state.search_mode {
                             match key.code {
                                 KeyCode::Esc => {
                                     self.state.search_mode = false;
                                     self.state.search_query.clear();
-                                    self.state.update_filtered_logs();
+                                    self.state.update_search();
                                 }
                                 KeyCode::Enter => {
                                     self.state.search_mode = false;
+                                    if self.state.search_pattern.is_some() {
+                                        self.state.find_next(true);
+                                    }
+                                }
+                                KeyCode::Tab => {
+                                    self.state.search_regex = !self.state.search_regex;
+                                    self.state.update_search();
                                 }
                                 KeyCode::Char(c) => {
                                     self.state.search_query.push(c);
-                                    self.state.update_filtered_logs();
+                                    self.state.update_search();
                                 }
-                                KeyCode::Backspace => {
-                                    if !self.state.search_query.is_empty() {
-                                        self.state.search_query.pop();
-                                        self.state.update_filtered_logs();
-                                    }
+                                KeyCode::Backspace if !self.state.search_query.is_empty() => {
+                                    self.state.search_query.pop();
+                                    self.state.update_search();
                                 }
                                 _ => {}
                             }
@@ -388,14 +888,72 @@ impl Tui {
                                 KeyCode::Char('1') => self.state.current_view = View::Logs,
                                 KeyCode::Char('2') => self.state.current_view = View::Stats,
                                 KeyCode::Char('3') => self.state.current_view = View::Storage,
+                                KeyCode::Char('4') => self.state.current_view = View::Crashes,
                                 KeyCode::Char('/') => self.state.search_mode = true,
-                                KeyCode::Char(' ') => self.state.paused = !self.state.paused,
-                                KeyCode::Char('t') => self.state.tail_mode = !self.state.tail_mode,
-                                KeyCode::Up => {
-                                    if self.state.scroll > 0 {
-                                        self.state.tail_mode = false;  // Disable tail mode when scrolling up
-                                        self.state.scroll = self.state.scroll.saturating_sub(1);
+                                KeyCode::Char('n') | KeyCode::Char('N') if self.state.search_pattern.is_some() => {
+                                    self.state.find_next(key.code == KeyCode::Char('n'));
+                                }
+                                KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Char('-') => {
+                                    self.state.adjust_context(key.code != KeyCode::Char('-'));
+                                    let message = format!("Context: {} before, {} after", self.state.context_before, self.state.context_after);
+                                    self.state.status_message = Some((message, Instant::now()));
+                                }
+                                KeyCode::Char('&') if self.state.search_pattern.is_some() => {
+                                    self.state.toggle_search_filter();
+                                    let message = if self.state.search_filter { "Showing only matches" } else { "Showing all rows" };
+                                    self.state.status_message = Some((message.to_string(), Instant::now()));
+                                }
+                                KeyCode::Char(':') => {
+                                    self.state.command_mode = true;
+                                    self.state.command = match &self.state.query {
+                                        Some(query) => format!("filter {}", query.as_str()),
+                                        None => "filter ".to_string(),
+                                    };
+                                }
+                                KeyCode::Char('s') => {
+                                    self.state.command_mode = true;
+                                    self.state.command = match &self.state.filterspecs {
+                                        Some(specs) => format!("spec {}", specs.as_str()),
+                                        None => "spec ".to_string(),
+                                    };
+                                }
+                                KeyCode::Char(' ') => match &mut self.replay {
+                                    Some(replay) => replay.toggle_pause(),
+                                    None => self.state.paused = !self.state.paused,
+                                },
+                                KeyCode::Left => {
+                                    if let Some(replay) = &mut self.replay {
+                                        if replay.seek(-SEEK_STEP) {
+                                            self.state.reset();
+                                        }
+                                    }
+                                }
+                                KeyCode::Right => {
+                                    if let Some(replay) = &mut self.replay {
+                                        replay.seek(SEEK_STEP);
                                     }
+                                }
+                                KeyCode::Char('<') => {
+                                    if let Some(replay) = &mut self.replay {
+                                        replay.slower();
+                                    }
+                                }
+                                KeyCode::Char('>') => {
+                                    if let Some(replay) = &mut self.replay {
+                                        replay.faster();
+                                    }
+                                }
+                                KeyCode::Char('t') => self.state.tail_mode = !self.state.tail_mode,
+                                KeyCode::Up if self.state.current_view == View::Crashes => {
+                                    self.state.selected_crash = self.state.selected_crash.saturating_sub(1);
+                                },
+                                KeyCode::Down if self.state.current_view == View::Crashes => {
+                                    let last = self.state.crashes.len().saturating_sub(1);
+                                    self.state.selected_crash = (self.state.selected_crash + 1).min(last);
+                                },
+                                KeyCode::Up if self.state.scroll > 0 => {
+                                    self.state.tail_mode = false;  // Disable tail mode when scrolling up
+                                    self.state.scroll = self.state.scroll.saturating_sub(1);
                                 },
                                 KeyCode::Down => {
                                     let max_scroll = self.state.filtered_logs.len().saturating_sub(1);
@@ -414,11 +972,15 @@ impl Tui {
                                 KeyCode::Home | KeyCode::Char('g') => {
                                     self.state.scroll = 0;
                                 }
+                                KeyCode::Char('a') => self.state.toggle_level(LogLevel::Assert),
+                                KeyCode::Char('f') => self.state.toggle_level(LogLevel::Fatal),
                                 KeyCode::Char('e') => self.state.toggle_level(LogLevel::Error),
                                 KeyCode::Char('w') => self.state.toggle_level(LogLevel::Warning),
                                 KeyCode::Char('i') => self.state.toggle_level(LogLevel::Info),
                                 KeyCode::Char('d') => self.state.toggle_level(LogLevel::Debug),
                                 KeyCode::Char('v') => self.state.toggle_level(LogLevel::Verbose),
+                                KeyCode::Char('u') => self.state.toggle_level(LogLevel::Unknown),
+                                KeyCode::Char('D') => self.state.cycle_device_filter(),
                                 KeyCode::PageUp => {
                                     self.state.tail_mode = false;
                                     self.state.scroll = self.state.scroll.saturating_sub(10);
@@ -430,17 +992,18 @@ impl Tui {
                                         self.state.tail_mode = true;
                                     }
                                 },
+                                KeyCode::Char('y') if self.state.current_view == View::Crashes => {
+                                    if let (Some(clipboard), Some(crash)) = (&mut self.clipboard, self.state.crashes.get(self.state.selected_crash)) {
+                                        if clipboard.set_contents(crash.report()).is_ok() {
+                                            self.state.status_message = Some(("Crash report copied to clipboard".to_string(), Instant::now()));
+                                        }
+                                    }
+                                },
                                 KeyCode::Char('y') => {
                                     if let Some(clipboard) = &mut self.clipboard {
-                                        if let Some(&index) = self.state.filtered_logs.get(self.state.scroll) {
+                                        if let Some(&index) = self.state.filtered_logs.get(self.state.current_row()) {
                                             if let Some(log) = self.state.logs.get(index) {
-                                                let log_text = format!(
-                                                    "{} [{}] {}: {}",
-                                                    log.timestamp,
-                                                    log.tag,
-                                                    log.level.as_str(),
-                                                    log.message
-                                                );
+                                                let log_text = log.to_logcat_line();
                                                 if clipboard.set_contents(log_text).is_ok() {
                                                     // Show copy confirmation in status
                                                     self.state.status_message = Some(("Log copied to clipboard".to_string(), Instant::now()));
@@ -452,15 +1015,9 @@ impl Tui {
                                 KeyCode::Char('c') => {
                                     // Command+C: Copy current log
                                     if let Some(clipboard) = &mut self.clipboard {
-                                        if let Some(&index) = self.state.filtered_logs.get(self.state.scroll) {
+                                        if let Some(&index) = self.state.filtered_logs.get(self.state.current_row()) {
                                             if let Some(log) = self.state.logs.get(index) {
-                                                let log_text = format!(
-                                                    "{} [{}] {}: {}",
-                                                    log.timestamp,
-                                                    log.tag,
-                                                    log.level.as_str(),
-                                                    log.message
-                                                );
+                                                let log_text = log.to_logcat_line();
                                                 if clipboard.set_contents(log_text).is_ok() {
                                                     self.state.status_message = Some(("Log copied to clipboard".to_string(), Instant::now()));
                                                 }
@@ -468,8 +1025,8 @@ impl Tui {
                                         }
                                     }
                                 },
-                                KeyCode::Char('n') => {
-                                    // Command+N: Toggle notifications
+                                KeyCode::Char('m') => {
+                                    // Command+M: Toggle notifications
                                     self.state.notify_on_error = !self.state.notify_on_error;
                                     self.state.status_message = Some((
                                         format!("Notifications {}", if self.state.notify_on_error { "enabled" } else { "disabled" }),
@@ -482,11 +1039,9 @@ impl Tui {
                     },
                     Event::Mouse(MouseEvent { kind, .. }) => {
                         match kind {
-                            MouseEventKind::ScrollUp => {
-                                if self.state.scroll > 0 {
-                                    self.state.tail_mode = false;
-                                    self.state.scroll = self.state.scroll.saturating_sub(3);
-                                }
+                            MouseEventKind::ScrollUp if self.state.scroll > 0 => {
+                                self.state.tail_mode = false;
+                                self.state.scroll = self.state.scroll.saturating_sub(3);
                             }
                             MouseEventKind::ScrollDown => {
                                 let max_scroll = self.state.filtered_logs.len().saturating_sub(1);
@@ -509,6 +1064,7 @@ impl Tui {
 
     fn draw(&mut self) -> io::Result<()> {
         let status = self.get_status();  // Get status before terminal.draw
+        let header = self.source.header();
         self.terminal.draw(|f| {
             let size = f.size();
             let main_block = Block::default()
@@ -528,12 +1084,13 @@ impl Tui {
                 .split(size);
 
             f.render_widget(main_block, size);
-            Self::draw_tabs(f, main_layout[0], self.state.current_view);
+            Self::draw_tabs(f, main_layout[0], self.state.current_view, header.as_deref());
             
             match self.state.current_view {
                 View::Logs => Self::draw_logs(f, main_layout[1], &self.state),
                 View::Stats => Self::draw_stats(f, main_layout[1], &self.state),
                 View::Storage => Self::draw_storage(f, main_layout[1], &self.state),
+                View::Crashes => Self::draw_crashes(f, main_layout[1], &self.state),
             }
 
             let status_widget = Paragraph::new(status)
@@ -545,14 +1102,19 @@ impl Tui {
         Ok(())
     }
 
-    fn draw_tabs(f: &mut Frame, area: Rect, current_view: View) {
-        let titles = vec!["Logs", "Stats", "Storage"];
+    fn draw_tabs(f: &mut Frame, area: Rect, current_view: View, header: Option<&str>) {
+        let titles = vec!["Logs", "Stats", "Storage", "Crashes"];
+        let title = match header {
+            Some(header) => format!("Views | {}", header),
+            None => "Views".to_string(),
+        };
         let tabs = Tabs::new(titles)
-            .block(Block::default().borders(Borders::ALL).title("Views"))
+            .block(Block::default().borders(Borders::ALL).title(title))
             .select(match current_view {
                 View::Logs => 0,
                 View::Stats => 1,
                 View::Storage => 2,
+                View::Crashes => 3,
             })
             .style(Style::default().fg(Color::White))
             .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
@@ -572,20 +1134,31 @@ impl Tui {
             state.scroll
         };
 
-        let visible_logs: Vec<ListItem> = state.filtered_logs
+        let mut visible_logs: Vec<ListItem> = state.filtered_logs
             .iter()
+            .enumerate()
             .skip(start_index)
             .take(max_display as usize)
-            .filter_map(|&index| state.logs.get(index))
-            .map(|log| {
+            .filter_map(|(position, &index)| Some((position, index, state.logs.get(index)?)))
+            .flat_map(|(position, index, log)| {
                 // Fixed widths for each component
                 const TIMESTAMP_WIDTH: usize = 19;
                 const TAG_WIDTH: usize = 8;
                 const LEVEL_WIDTH: usize = 5;
+                const DEVICE_WIDTH: usize = 14;
                 const PADDING: usize = 7;  // For brackets, spaces, and colon
 
+                // The device column only appears once entries from several devices are mixed
+                let device = if state.devices.len() > 1 {
+                    let serial = log.device.as_deref().unwrap_or("?");
+                    format!("{:<width$} ", serial.chars().take(DEVICE_WIDTH).collect::<String>(), width = DEVICE_WIDTH)
+                } else {
+                    String::new()
+                };
+
                 // Calculate remaining width for message
                 let message_width = (inner_width as usize)
+                    .saturating_sub(device.chars().count())
                     .saturating_sub(TIMESTAMP_WIDTH)
                     .saturating_sub(TAG_WIDTH)
                     .saturating_sub(LEVEL_WIDTH)
@@ -593,36 +1166,59 @@ impl Tui {
                     .saturating_sub(2);  // Account for icon and space
 
                 // Get the icon for the log level
-                let icon = match log.level {
-                    LogLevel::Error => "🔴",
-                    LogLevel::Warning => "⚠️",
-                    LogLevel::Info => "ℹ️",
-                    LogLevel::Debug => "🔧",
-                    LogLevel::Verbose => "📝",
-                    LogLevel::Unknown => "❓",
-                };
+                let icon = log.level.icon();
 
-                let line = format!(
-                    "{} {:<width$} [{:<tag_width$}] {:<level_width$}: {:.message_width$}",
+                let tag = format!("{:<width$}", log.tag.chars().take(TAG_WIDTH).collect::<String>(), width = TAG_WIDTH);
+                let message: String = log.message
+                    .replace('\n', " ⏎ ")  // Keep coalesced entries on one row
+                    .chars()
+                    .take(message_width)
+                    .collect();
+
+                // Search matches in the tag and message are picked out
+                let pattern = state.search_pattern.as_ref();
+                let mut spans = vec![Span::raw(format!(
+                    "{} {}{:<width$} [",
                     icon,
-                    log.timestamp,
-                    log.tag.chars().take(TAG_WIDTH).collect::<String>(),
-                    log.level.as_str(),
-                    log.message,
+                    device,
+                    log.timestamp.format(TIMESTAMP_FORMAT).to_string(),
                     width = TIMESTAMP_WIDTH,
-                    tag_width = TAG_WIDTH,
-                    level_width = LEVEL_WIDTH,
-                    message_width = message_width
-                );
-                
-                ListItem::new(line).style(Style::default().fg(log.level.color()))
+                ))];
+                spans.extend(Self::highlight(tag, pattern));
+                spans.push(Span::raw(format!("] {:<width$}: ", log.level.as_str(), width = LEVEL_WIDTH)));
+                spans.extend(Self::highlight(message, pattern));
+
+                // Context rows are dimmed; the current match has a highlighted background
+                let style = if state.context_rows.contains(&index) {
+                    Style::default().fg(Color::DarkGray)
+                } else {
+                    Style::default().fg(log.level.color())
+                };
+                let style = if state.find_cursor == Some(position) { style.bg(Color::DarkGray) } else { style };
+                let item = ListItem::new(Line::from(spans)).style(style);
+
+                // A `--` row splits context groups that aren't adjacent, except at the top
+                if position > start_index && state.separators.contains(&index) {
+                    vec![ListItem::new("--").style(Style::default().fg(Color::DarkGray)), item]
+                } else {
+                    vec![item]
+                }
             })
             .collect();
 
+        // Separators take rows too; in tail mode drop them from the top so the newest
+        // entries stay visible
+        let excess = visible_logs.len().saturating_sub(max_display as usize);
+        if state.tail_mode {
+            visible_logs.drain(..excess);
+        } else {
+            visible_logs.truncate(max_display as usize);
+        }
+
         let title = if state.search_mode {
             format!(" Log Output (Searching: '{}', {} matches) ", 
                 state.search_query,
-                state.filtered_logs.len()
+                state.find_matches.len()
             )
         } else {
             format!(" Log Output ({} logs) ", state.filtered_logs.len())
@@ -638,25 +1234,63 @@ impl Tui {
         f.render_widget(logs, area);
     }
 
+    // The text as spans with each non-empty match of the pattern highlighted
+    fn highlight(text: String, pattern: Option<&Regex>) -> Vec<Span<'static>> {
+        let Some(pattern) = pattern else {
+            return vec![Span::raw(text)];
+        };
+        let match_style = Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD);
+        let mut spans = Vec::new();
+        let mut end = 0;
+        for found in pattern.find_iter(&text).filter(|found| !found.is_empty()) {
+            spans.push(Span::raw(text[end..found.start()].to_string()));
+            spans.push(Span::styled(found.as_str().to_string(), match_style));
+            end = found.end();
+        }
+        spans.push(Span::raw(text[end..].to_string()));
+        spans
+    }
+
     fn draw_stats(f: &mut Frame, area: Rect, state: &AppState) {
         let stats = format!(
             "\nLog Statistics:\n\
             \n\
+            🚨 Asserts:  {}\n\
+            💀 Fatal:    {}\n\
             🔴 Errors:   {}\n\
             ⚠️  Warnings: {}\n\
             ℹ️  Info:     {}\n\
             🔧 Debug:    {}\n\
             📝 Verbose:  {}\n\
+            ⏳ ANRs:     {}\n\
             \n\
             Total Logs: {}\n\
-            Memory Usage: {} entries",
+            Memory Usage: {} entries\n\
+            {}",
+            state.stats.assert_count,
+            state.stats.fatal_count,
             state.stats.error_count,
             state.stats.warning_count,
             state.stats.info_count,
             state.stats.debug_count,
             state.stats.verbose_count,
+            state.stats.anr_count,
             state.logs.len(),
             state.logs.capacity(),
+            state.ingest.as_ref().map_or(String::new(), |ingest| format!(
+                "\nIngestion:\n\
+                \n\
+                Lines/sec:      {:.0}\n\
+                Dropped:        {}\n\
+                Parse failures: {}\n\
+                Queue:          {}/{} ({})",
+                ingest.lines_per_sec,
+                ingest.dropped,
+                ingest.parse_failures,
+                ingest.queued,
+                ingest.capacity,
+                ingest.policy.as_str(),
+            )),
         );
 
         let stats_widget = Paragraph::new(stats)
@@ -687,10 +1321,57 @@ impl Tui {
         f.render_widget(storage_widget, area);
     }
 
+    fn draw_crashes(f: &mut Frame, area: Rect, state: &AppState) {
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)].as_ref())
+            .split(area);
+
+        let items: Vec<ListItem> = state.crashes
+            .iter()
+            .enumerate()
+            .map(|(i, crash)| {
+                let line = crash.summary();
+                let style = if i == state.selected_crash {
+                    Style::default().fg(Color::Red).bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::Red)
+                };
+                ListItem::new(line).style(style)
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" Crashes ({}) ", state.crashes.len()))
+                .border_type(ratatui::widgets::BorderType::Rounded));
+        f.render_widget(list, layout[0]);
+
+        let detail = match state.crashes.get(state.selected_crash) {
+            Some(crash) => crash.report(),
+            None => "\nNo crashes detected".to_string(),
+        };
+        let detail_widget = Paragraph::new(detail)
+            .block(Block::default().borders(Borders::ALL).title("Crash Detail"))
+            .wrap(ratatui::widgets::Wrap { trim: false })
+            .style(Style::default().fg(Color::White));
+        f.render_widget(detail_widget, layout[1]);
+    }
+
     // New method to get status without borrowing self mutably
     fn get_status(&self) -> String {
-        if self.state.search_mode {
-            format!("Search: {} | Press Enter to confirm or Esc to cancel", self.state.search_query)
+        if self.state.command_mode {
+            match &self.state.command_error {
+                Some(error) => format!(":{} | {}", self.state.command, error.red()),
+                None => format!(":{} | Press Enter to apply or Esc to cancel", self.state.command),
+            }
+        } else if self.state.search_mode {
+            let mode = if self.state.search_text().0 { "Regex search" } else { "Search" };
+            match &self.state.search_error {
+                Some(error) => format!("{}: {} | {}", mode, self.state.search_query, error.red()),
+                None => format!("{}: {} | Tab: literal/regex | Press Enter to confirm or Esc to cancel", mode, self.state.search_query),
+            }
         } else if let Some((msg, time)) = &self.state.status_message {
             if time.elapsed().as_secs() > 2 {
                 self.draw_normal_status(&self.state)
@@ -705,18 +1386,22 @@ impl Tui {
     // Helper method for normal status
     fn draw_normal_status(&self, state: &AppState) -> String {
         let connection_indicator = match state.connection_status {
-            ConnectionStatus::Connected => format!("🟢 {}", "Connected".green()),
-            ConnectionStatus::Disconnected => format!("🔴 {}", "Disconnected".red()),
-            ConnectionStatus::Error => format!("⚠️  {}", "Error".yellow()),
+            Some(ConnectionStatus::Connected) => format!("🟢 {}", "Connected".green()),
+            Some(ConnectionStatus::Disconnected) => format!("🔴 {}", "Disconnected".red()),
+            Some(ConnectionStatus::Error) => format!("⚠️  {}", "Error".yellow()),
+            None => format!("📄 {}", self.source.describe()),
         };
 
         // Add spaces between filter indicators for better readability
-        let filters = format!("[{} {} {} {} {}]",
+        let filters = format!("[{} {} {} {} {} {} {} {}]",
+            if state.level_filters.contains(&LogLevel::Assert) { "A".bright_magenta() } else { "-".dimmed() },
+            if state.level_filters.contains(&LogLevel::Fatal) { "F".magenta() } else { "-".dimmed() },
             if state.level_filters.contains(&LogLevel::Error) { "E".red() } else { "-".dimmed() },
             if state.level_filters.contains(&LogLevel::Warning) { "W".yellow() } else { "-".dimmed() },
             if state.level_filters.contains(&LogLevel::Info) { "I".green() } else { "-".dimmed() },
             if state.level_filters.contains(&LogLevel::Debug) { "D".blue() } else { "-".dimmed() },
             if state.level_filters.contains(&LogLevel::Verbose) { "V".white() } else { "-".dimmed() },
+            if state.level_filters.contains(&LogLevel::Unknown) { "?".bright_black() } else { "-".dimmed() },
         );
 
         let status = if state.paused { "PAUSED".red() } else { "RUNNING".green() };
@@ -724,6 +1409,49 @@ impl Tui {
         let position = format!("{:>3}/{:<3}", state.scroll + 1, state.filtered_logs.len());
         let log_count = format!("{:>3} logs", state.filtered_logs.len());
 
+        let filters = match &state.device_filter {
+            Some(device) => format!("{} {}", filters, device.cyan()),
+            None => filters,
+        };
+        let filters = match &state.query {
+            Some(query) => format!("{} {}", filters, query.as_str().cyan()),
+            None => filters,
+        };
+        let filters = match &state.filterspecs {
+            Some(specs) => format!("{} {}", filters, specs.as_str().cyan()),
+            None => filters,
+        };
+        let filters = match (state.context_before, state.context_after) {
+            (0, 0) => filters,
+            (before, after) if before == after => format!("{} {}", filters, format!("±{}", before).cyan()),
+            (before, after) => format!("{} {}", filters, format!("-{} +{}", before, after).cyan()),
+        };
+        let filters = match state.find_status() {
+            Some(find) if state.search_filter => format!("{} {} {}", filters, find.cyan(), "(only matches)".dimmed()),
+            Some(find) => format!("{} {}", filters, find.cyan()),
+            None => filters,
+        };
+
+        let status = match &self.replay {
+            Some(replay) => replay.status().cyan(),
+            None => status,
+        };
+
+        // Throughput, plus a warning once anything has been lost or failed to parse
+        let status = match &state.ingest {
+            Some(ingest) => {
+                let mut health = format!("{} {:.0}/s", status, ingest.lines_per_sec);
+                if ingest.dropped > 0 {
+                    health.push_str(&format!(" {}", format!("⚠ {} dropped", ingest.dropped).red()));
+                }
+                if ingest.parse_failures > 0 {
+                    health.push_str(&format!(" {}", format!("{} unparsed", ingest.parse_failures).yellow()));
+                }
+                health.normal()
+            }
+            None => status,
+        };
+
         format!(
             "{} | {} | Filters {} | {} | {} | {} | {}",
             connection_indicator,
@@ -737,7 +1465,7 @@ impl Tui {
     }
 
     fn draw_help(f: &mut Frame, area: Rect) {
-        let help_text = "1-3: Views | Space: Pause | t: Tail | /: Search | y: Copy | n: Notifications | e/w/i/d/v: Filters | ↑/↓: Scroll | End/G: Latest | Home/g: First | q: Quit";
+        let help_text = "1-4: Views | Space: Pause | t: Tail | /: Search | :: Filter | s: Filterspecs | n/N: Next/Prev match | &: Only matches | +/-: Context | y: Copy | m: Notifications | a/f/e/w/i/d/v/u: Filters | D: Device | ↑/↓: Scroll | End/G: Latest | Home/g: First | q: Quit";
         let help = Paragraph::new(help_text)
             .block(Block::default().borders(Borders::ALL))
             .style(Style::default().fg(Color::Gray));
@@ -757,4 +1485,276 @@ impl Drop for Tui {
             .execute(DisableMouseCapture)
             .unwrap();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_log_entry;
+
+    fn state_with(lines: &[&str]) -> AppState {
+        let mut state = AppState::new();
+        state.add_logs(lines.iter().map(|line| parse_log_entry(line)));
+        state
+    }
+
+    fn visible(state: &AppState) -> Vec<&str> {
+        state.filtered_logs.iter().map(|&i| state.logs[i].message.as_str()).collect()
+    }
+
+    #[test]
+    fn unparsed_lines_are_shown_by_default() {
+        let state = state_with(&[
+            "--------- beginning of main",
+            "03-21 10:23:45.678  1234  1234 I Foo: parsed",
+            "just some pasted text",
+        ]);
+        assert_eq!(visible(&state), ["--------- beginning of main", "parsed", "just some pasted text"]);
+    }
+
+    #[test]
+    fn unparsed_lines_can_be_hidden() {
+        let mut state = state_with(&["03-21 10:23:45.678  1234  1234 I Foo: parsed", "raw line"]);
+        state.toggle_level(LogLevel::Unknown);
+        assert_eq!(visible(&state), ["parsed"]);
+        state.toggle_level(LogLevel::Unknown);
+        assert_eq!(visible(&state), ["parsed", "raw line"]);
+    }
+
+    fn search(state: &mut AppState, text: &str) {
+        state.search_query = text.to_string();
+        state.update_search();
+    }
+
+    fn found(state: &AppState) -> Vec<&str> {
+        state.find_matches.iter().map(|&p| state.logs[state.filtered_logs[p]].message.as_str()).collect()
+    }
+
+    const SEARCHABLE: &[&str] = &[
+        "03-21 10:23:45.678  1234  1234 I Net: Connected to host",
+        "03-21 10:23:45.679  1234  1234 W Net: connection reset (a.b)",
+        "03-21 10:23:45.680  1234  1234 I Disk: aXb written",
+    ];
+
+    #[test]
+    fn literal_search_ignores_case_and_escapes() {
+        let mut state = state_with(SEARCHABLE);
+        search(&mut state, "CONNECT");
+        assert_eq!(found(&state), ["Connected to host", "connection reset (a.b)"]);
+        search(&mut state, "a.b");
+        assert_eq!(found(&state), ["connection reset (a.b)"]);
+        search(&mut state, "(a");
+        assert_eq!((found(&state), state.search_error.is_none()), (vec!["connection reset (a.b)"], true));
+        // The level name and tag are searched too
+        search(&mut state, "disk");
+        assert_eq!(found(&state), ["aXb written"]);
+    }
+
+    #[test]
+    fn regex_search_by_prefix_or_toggle() {
+        let mut state = state_with(SEARCHABLE);
+        search(&mut state, "re:^conn.*reset");
+        assert_eq!(found(&state), ["connection reset (a.b)"]);
+        search(&mut state, "re:a.b");
+        assert_eq!(found(&state), ["connection reset (a.b)", "aXb written"]);
+
+        state.search_regex = true;
+        search(&mut state, "HOST$");
+        assert_eq!(found(&state), ["Connected to host"]);
+    }
+
+    #[test]
+    fn invalid_regex_keeps_the_previous_pattern() {
+        let mut state = state_with(SEARCHABLE);
+        search(&mut state, "re:host");
+        search(&mut state, "re:host(");
+        assert!(state.search_error.as_deref().is_some_and(|error| error.starts_with("Invalid regex: ")));
+        assert_eq!(state.search_pattern.as_ref().map(Regex::as_str), Some("host"));
+        assert_eq!(found(&state), ["Connected to host"]);
+
+        search(&mut state, "re:host(s)?");
+        assert!(state.search_error.is_none());
+        search(&mut state, "");
+        assert!(state.search_pattern.is_none() && state.search_error.is_none());
+        assert!(state.find_matches.is_empty());
+    }
+
+    #[test]
+    fn search_filter_hides_other_rows() {
+        let mut state = state_with(SEARCHABLE);
+        search(&mut state, "connect");
+        assert_eq!(visible(&state).len(), 3);
+        state.toggle_search_filter();
+        assert_eq!(visible(&state), ["Connected to host", "connection reset (a.b)"]);
+    }
+
+    #[test]
+    fn highlight_splits_around_matches() {
+        let spans = |text: &str, pattern: Option<&Regex>| -> Vec<(String, bool)> {
+            Tui::highlight(text.to_string(), pattern)
+                .into_iter()
+                .map(|span| (span.content.into_owned(), span.style.bg == Some(Color::Yellow)))
+                .collect()
+        };
+        let owned = |parts: &[(&str, bool)]| -> Vec<(String, bool)> {
+            parts.iter().map(|(text, hit)| (text.to_string(), *hit)).collect()
+        };
+        let pattern = RegexBuilder::new("ab").case_insensitive(true).build().unwrap();
+
+        assert_eq!(spans("xxABxabx", Some(&pattern)), owned(&[("xx", false), ("AB", true), ("x", false), ("ab", true), ("x", false)]));
+        assert_eq!(spans("ab", Some(&pattern)), owned(&[("", false), ("ab", true), ("", false)]));
+        assert_eq!(spans("none here", Some(&pattern)), owned(&[("none here", false)]));
+        assert_eq!(spans("ab", None), owned(&[("ab", false)]));
+
+        // Empty matches aren't highlighted
+        let empty = Regex::new("x*").unwrap();
+        assert_eq!(spans("ab", Some(&empty)), owned(&[("ab", false)]));
+    }
+
+    const FINDABLE: &[&str] = &[
+        "03-21 10:23:45.000  1234  1234 I App: miss 0",
+        "03-21 10:23:45.001  1234  1234 I App: hit 1",
+        "03-21 10:23:45.002  1234  1234 I App: miss 2",
+        "03-21 10:23:45.003  1234  1234 I App: hit 3",
+        "03-21 10:23:45.004  1234  1234 I App: miss 4",
+        "03-21 10:23:45.005  1234  1234 I App: hit 5",
+    ];
+
+    fn cursor_message(state: &AppState) -> Option<&str> {
+        state.find_cursor.map(|position| state.logs[state.filtered_logs[position]].message.as_str())
+    }
+
+    #[test]
+    fn find_wraps_in_both_directions() {
+        let mut state = state_with(FINDABLE);
+        search(&mut state, "hit");
+        assert_eq!(state.find_status().as_deref(), Some("3 matches"));
+
+        // Tail mode starts from the newest row
+        assert!(state.tail_mode);
+        state.find_next(true);
+        assert_eq!((cursor_message(&state), state.tail_mode), (Some("hit 5"), false));
+        assert_eq!(state.find_status().as_deref(), Some("match 3/3"));
+
+        state.find_next(true);
+        assert_eq!(cursor_message(&state), Some("hit 1"));
+        assert_eq!(state.find_status().as_deref(), Some("match 1/3"));
+        state.find_next(false);
+        assert_eq!(cursor_message(&state), Some("hit 5"));
+        state.find_next(false);
+        assert_eq!(cursor_message(&state), Some("hit 3"));
+        assert_eq!(state.current_row(), 3);
+    }
+
+    #[test]
+    fn find_starts_from_the_top_row_outside_tail_mode() {
+        let mut state = state_with(FINDABLE);
+        state.tail_mode = false;
+        state.scroll = 2;
+        search(&mut state, "hit");
+        state.find_next(true);
+        assert_eq!(cursor_message(&state), Some("hit 3"));
+
+        search(&mut state, "hit");
+        state.scroll = 2;
+        state.find_next(false);
+        assert_eq!(cursor_message(&state), Some("hit 1"));
+    }
+
+    #[test]
+    fn find_without_matches() {
+        let mut state = state_with(FINDABLE);
+        assert_eq!(state.find_status(), None);
+        search(&mut state, "nothing");
+        state.find_next(true);
+        assert_eq!(state.find_cursor, None);
+        assert_eq!(state.status_message.as_ref().map(|(message, _)| message.as_str()), Some("No matches"));
+        assert_eq!(state.find_status().as_deref(), Some("0 matches"));
+    }
+
+    #[test]
+    fn refiltering_keeps_the_current_match() {
+        let mut state = state_with(FINDABLE);
+        search(&mut state, "hit");
+        state.find_next(true);
+        state.find_next(false);
+        assert_eq!(cursor_message(&state), Some("hit 3"));
+
+        state.toggle_search_filter();
+        assert_eq!(visible(&state), ["hit 1", "hit 3", "hit 5"]);
+        assert_eq!((state.find_cursor, cursor_message(&state)), (Some(1), Some("hit 3")));
+        assert_eq!(state.find_status().as_deref(), Some("match 2/3"));
+
+        state.adjust_context(true);
+        assert_eq!(visible(&state).len(), 6);
+        assert_eq!((state.find_cursor, cursor_message(&state)), (Some(3), Some("hit 3")));
+
+        state.adjust_context(false);
+        state.toggle_search_filter();
+        assert_eq!((state.find_cursor, cursor_message(&state)), (Some(3), Some("hit 3")));
+    }
+
+    #[test]
+    fn device_filter_cycles_back_to_all() {
+        let mut state = AppState::new();
+        state.add_logs(["emulator-5554", "28011FDH2000B9", "emulator-5554"].iter().map(|serial| {
+            let mut entry = parse_log_entry(&format!("03-21 10:23:45.678  1234  1234 I App: from {}", serial));
+            entry.device = Some(serial.to_string());
+            entry
+        }));
+        assert_eq!(state.devices, ["emulator-5554", "28011FDH2000B9"]);
+        assert_eq!(visible(&state).len(), 3);
+
+        state.cycle_device_filter();
+        assert_eq!(state.device_filter.as_deref(), Some("emulator-5554"));
+        assert_eq!(visible(&state), ["from emulator-5554", "from emulator-5554"]);
+        state.cycle_device_filter();
+        assert_eq!(visible(&state), ["from 28011FDH2000B9"]);
+        state.cycle_device_filter();
+        assert_eq!((state.device_filter.as_deref(), visible(&state).len()), (None, 3));
+    }
+
+    const LEVELS: &[LogLevel] = &[
+        LogLevel::Unknown, LogLevel::Verbose, LogLevel::Debug, LogLevel::Info, LogLevel::Warning,
+        LogLevel::Error, LogLevel::Fatal, LogLevel::Assert, LogLevel::Silent,
+    ];
+
+    #[test]
+    fn levels_are_in_priority_order() {
+        assert!(LEVELS.windows(2).all(|pair| pair[0] < pair[1]));
+        let at_least_warning: Vec<char> = LEVELS.iter().filter(|&&l| l >= LogLevel::Warning).map(LogLevel::priority).collect();
+        assert_eq!(at_least_warning, ['W', 'E', 'F', 'A', 'S']);
+        for &level in LEVELS {
+            assert_eq!(LogLevel::from_priority(level.priority()), level);
+        }
+        assert_eq!(LogLevel::from_priority('x'), LogLevel::Unknown);
+    }
+
+    #[test]
+    fn level_names() {
+        assert_eq!(LogLevel::from_name("w"), Some(LogLevel::Warning));
+        assert_eq!(LogLevel::from_name("warn"), Some(LogLevel::Warning));
+        assert_eq!(LogLevel::from_name("Warning"), Some(LogLevel::Warning));
+        assert_eq!(LogLevel::from_name("fatal"), Some(LogLevel::Fatal));
+        assert_eq!(LogLevel::from_name("A"), Some(LogLevel::Assert));
+        assert_eq!(LogLevel::from_name("silent"), Some(LogLevel::Silent));
+        assert_eq!(LogLevel::from_name("?"), None);
+        assert_eq!(LogLevel::from_name("unknown"), None);
+        assert_eq!(LogLevel::from_name("loud"), None);
+    }
+
+    #[test]
+    fn levels_serialize_as_their_names() {
+        // Sessions saved before the level was typed stored these strings
+        for (name, level) in [
+            ("ERROR", LogLevel::Error), ("WARN", LogLevel::Warning), ("INFO", LogLevel::Info),
+            ("DEBUG", LogLevel::Debug), ("VERBOSE", LogLevel::Verbose), ("UNKNOWN", LogLevel::Unknown),
+        ] {
+            assert_eq!(serde_json::from_str::<LogLevel>(&format!("\"{}\"", name)).unwrap(), level);
+        }
+        for &level in LEVELS {
+            let json = serde_json::to_string(&level).unwrap();
+            assert_eq!(json, format!("\"{}\"", level.as_str()));
+            assert_eq!(serde_json::from_str::<LogLevel>(&json).unwrap(), level);
+        }
+    }
+}
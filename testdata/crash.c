@@ -0,0 +1,13 @@
+// Source of libcrash.so, the unstripped library the symbolize tests resolve against.
+// Rebuild from this directory with:
+//   gcc -g -O0 -shared -fPIC -fdebug-prefix-map=$PWD=testdata -o libcrash.so crash.c
+
+int crash_here(int *pointer) {
+    int value = 1;
+    value += *pointer;
+    return value;
+}
+
+int call_crash(void) {
+    return crash_here(0);
+}